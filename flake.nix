@@ -20,7 +20,7 @@
       in {
         packages.treemerge = rustPlatform.buildRustPackage {
           pname = "treemerge";
-          version = "0.0.4";
+          version = "0.0.5";
           src = self;
 
           cargoLock = {
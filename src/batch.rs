@@ -0,0 +1,250 @@
+use crate::cli::{Args, HeaderStyle};
+use crate::config;
+use crate::interrupt::Interrupted;
+use crate::merge::{self, human_size};
+use crate::remote;
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A jobs file for `treemerge batch`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Jobs {
+    /// Options for every job, ahead of the job's own
+    #[serde(default)]
+    flags: Vec<String>,
+    job: Vec<Job>,
+}
+
+/// One merge of a batch
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Job {
+    /// Shown in the report (default: the output's file stem)
+    name: Option<String>,
+    /// Directory, relative to the jobs file, or a git URL
+    root: String,
+    /// Relative to the jobs file (default: NAME.txt next to it)
+    output: Option<PathBuf>,
+    /// `--profile` of the root's treemerge.toml
+    profile: Option<String>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    ext: Vec<String>,
+    header_style: Option<HeaderStyle>,
+    split_every: Option<usize>,
+    /// Any other options
+    #[serde(default)]
+    flags: Vec<String>,
+}
+
+/// How one job went, for the report
+#[derive(Serialize)]
+struct Outcome {
+    name: String,
+    root: String,
+    /// Set when the job failed
+    error: Option<String>,
+    files: usize,
+    outputs: Vec<PathBuf>,
+    bytes: u64,
+    seconds: f64,
+}
+
+impl Job {
+    /// The job's command line, before the configured defaults
+    fn argv(&self, dir: &Path, shared: &[String], output: &Path) -> Vec<OsString> {
+        let local = dir.join(&self.root);
+        let root: OsString = if local.is_dir() || remote::parse(Path::new(&self.root)).is_none() {
+            local.into()
+        } else {
+            self.root.clone().into()
+        };
+        let mut argv: Vec<OsString> = vec!["treemerge".into(), root];
+        argv.extend(shared.iter().map(OsString::from));
+        let mut push = |flag: &str, value: OsString| {
+            argv.push(flag.into());
+            argv.push(value);
+        };
+        for pattern in &self.include {
+            push("--include", pattern.into());
+        }
+        for pattern in &self.exclude {
+            push("--exclude", pattern.into());
+        }
+        for ext in &self.ext {
+            push("--ext", ext.into());
+        }
+        if let Some(value) = self.header_style.and_then(|s| s.to_possible_value()) {
+            push("--header-style", value.get_name().into());
+        }
+        if let Some(lines) = self.split_every {
+            push("--split-every", lines.to_string().into());
+        }
+        if let Some(profile) = &self.profile {
+            push("--profile", profile.into());
+        }
+        push("--output", output.into());
+        argv.extend(self.flags.iter().map(OsString::from));
+        argv
+    }
+}
+
+/// Run one job; its merge prints as a merge on the command line would
+fn run_job(argv: Vec<OsString>, output: &Path) -> Result<merge::Merged> {
+    // Packs often go to a directory of their own
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).with_context(|| format!("cannot create {}", parent.display()))?;
+    }
+    let argv = config::merge_argv(argv)?;
+    let args = Args::try_parse_from(argv)?;
+    if args.command.is_some() {
+        return Err(anyhow!("jobs cannot run subcommands"));
+    }
+    merge::run(args.into())
+}
+
+/// `treemerge batch`: the jobs of `file` in order, in one process sharing
+/// remote clones and text detection, then a report on every job
+///
+/// A failed job is reported and the next one runs, unless `fail_fast`;
+/// the batch fails when any job did.
+pub fn run(file: &Path, report: Option<&Path>, fail_fast: bool) -> Result<()> {
+    let text = fs::read_to_string(file).with_context(|| format!("cannot read {}", file.display()))?;
+    let jobs: Jobs = toml::from_str(&text).with_context(|| format!("invalid jobs file {}", file.display()))?;
+    if jobs.job.is_empty() {
+        return Err(anyhow!("{}: no [[job]] tables", file.display()));
+    }
+    let dir = file.parent().unwrap_or(Path::new(""));
+
+    remote::share_clones();
+    merge::share_text_detection();
+    let started = Instant::now();
+    let mut outcomes = Vec::new();
+    let mut interrupted = None;
+    for (index, job) in jobs.job.iter().enumerate() {
+        let name = job.name.clone().unwrap_or_else(|| match &job.output {
+            Some(output) => output.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            None => format!("job{}", index + 1),
+        });
+        let output = dir.join(job.output.clone().unwrap_or_else(|| format!("{}.txt", name).into()));
+        eprintln!("[{}/{}] {}", index + 1, jobs.job.len(), name);
+
+        let start = Instant::now();
+        let result = run_job(job.argv(dir, &jobs.flags, &output), &output);
+        let mut outcome = Outcome {
+            name,
+            root: job.root.clone(),
+            error: None,
+            files: 0,
+            outputs: Vec::new(),
+            bytes: 0,
+            seconds: start.elapsed().as_secs_f64(),
+        };
+        match result {
+            Ok(merged) => {
+                outcome.files = merged.files;
+                outcome.bytes = merged.outputs.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+                outcome.outputs = merged.outputs;
+            }
+            Err(e) => {
+                eprintln!("{}: {:#}", outcome.name, e);
+                outcome.error = Some(format!("{:#}", e));
+                if e.is::<Interrupted>() {
+                    interrupted = Some(e);
+                }
+            }
+        }
+        let failed = outcome.error.is_some();
+        outcomes.push(outcome);
+        if interrupted.is_some() || (failed && fail_fast) {
+            break;
+        }
+    }
+    remote::drop_clones();
+
+    print_report(&outcomes, jobs.job.len());
+    if let Some(path) = report {
+        let json = serde_json::json!({
+            "jobs": outcomes,
+            "seconds": started.elapsed().as_secs_f64(),
+        });
+        fs::write(path, serde_json::to_string_pretty(&json)? + "\n")
+            .with_context(|| format!("cannot write {}", path.display()))?;
+    }
+    if let Some(e) = interrupted {
+        return Err(e);
+    }
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} jobs failed", failed, jobs.job.len()));
+    }
+    Ok(())
+}
+
+/// One line per job, then the totals
+fn print_report(outcomes: &[Outcome], total: usize) {
+    let width = outcomes.iter().map(|o| o.name.chars().count()).max().unwrap_or(0).max(3);
+    println!("{:<width$}  {:>7}  {:>10}  {:>7}  status", "job", "files", "size", "time");
+    for o in outcomes {
+        let status = match &o.error {
+            Some(e) => format!("failed: {}", e.lines().next().unwrap_or_default()),
+            None if o.outputs.len() == 1 => "ok".to_string(),
+            None => format!("ok ({} outputs)", o.outputs.len()),
+        };
+        println!(
+            "{:<width$}  {:>7}  {:>10}  {:>6.1}s  {}",
+            o.name,
+            o.files,
+            human_size(o.bytes),
+            o.seconds,
+            status
+        );
+    }
+    let ok = outcomes.iter().filter(|o| o.error.is_none()).count();
+    let skipped = total - outcomes.len();
+    let mut summary = format!("{} of {} jobs succeeded", ok, total);
+    if skipped > 0 {
+        summary.push_str(&format!(", {} not run", skipped));
+    }
+    println!("{}", summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_run_in_order_and_report() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/a.txt"), "a\n").unwrap();
+        fs::write(dir.path().join("src/b.md"), "b\n").unwrap();
+        let jobs = dir.path().join("jobs.toml");
+        let text = "flags = [\"--no-config\"]\n\n[[job]]\nroot = \"src\"\nexclude = [\"*.md\"]\noutput = \"packs/src.txt\"\n\n\
+                    [[job]]\nname = \"missing\"\nroot = \"nowhere\"\n";
+        fs::write(&jobs, text).unwrap();
+        let report = dir.path().join("report.json");
+        let err = run(&jobs, Some(&report), false).unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 jobs failed");
+
+        let merged = fs::read_to_string(dir.path().join("packs/src.txt")).unwrap();
+        assert!(merged.contains("a.txt") && !merged.contains("b.md"), "{}", merged);
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(report["jobs"][0]["name"], "src");
+        assert_eq!(report["jobs"][0]["files"], 1);
+        assert_eq!(report["jobs"][1]["name"], "missing");
+        assert!(report["jobs"][1]["error"].is_string());
+
+        fs::write(&jobs, "flags = []\njob = []\n").unwrap();
+        assert!(run(&jobs, None, false).is_err());
+    }
+}
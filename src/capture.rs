@@ -0,0 +1,47 @@
+use crate::summarize::shell;
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+
+/// Output of a `--capture` command
+pub struct Capture {
+    pub stdout: String,
+    pub status: ExitStatus,
+}
+
+/// Run the shell command `command` in `dir`, collecting its stdout
+///
+/// A failing command is not an error: its output (often the interesting
+/// part, e.g. a build failure) is kept and the exit status reported.
+pub fn run(command: &str, dir: &Path) -> Result<Capture> {
+    let output = shell(command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("cannot run {:?}: {}", command, e))?;
+    let mut stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !stdout.is_empty() && !stdout.ends_with('\n') {
+        stdout.push('\n');
+    }
+    Ok(Capture {
+        stdout,
+        status: output.status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_commands_keep_their_output() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("marker"), "").unwrap();
+        let captured = run("ls; printf failed; exit 2", dir.path()).unwrap();
+        assert_eq!(captured.stdout, "marker\nfailed\n");
+        assert_eq!(captured.status.code(), Some(2));
+        assert_eq!(run("true", dir.path()).unwrap().stdout, "");
+    }
+}
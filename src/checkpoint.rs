@@ -0,0 +1,204 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const HEADER: &str = "treemerge scan checkpoint 1";
+/// How much walking an interruption may cost
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// Walk progress of a root, as recorded by an earlier run
+///
+/// The file is append-only: a `root` line opens the part written by one
+/// run, `f` lines name the files found and an `@` line marks everything up
+/// to it as durable. `done` closes a finished walk.
+#[derive(Default)]
+pub struct Resumed {
+    /// Root-relative files found so far
+    pub files: Vec<String>,
+    /// Last file covered by a marker; the walk continues after it
+    pub last: Option<String>,
+    /// The walk got to the end
+    pub complete: bool,
+}
+
+/// Name under which a root is recorded, the same however it was spelled
+fn key(root: &Path) -> String {
+    root.canonicalize()
+        .unwrap_or_else(|_| root.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// What `path` recorded for `root`; nothing when the file does not exist
+pub fn load(path: &Path, root: &Path) -> Result<Resumed> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Resumed::default()),
+        Err(e) => return Err(e).with_context(|| format!("cannot read {}", path.display())),
+    };
+    let mut lines = BufReader::new(file).lines();
+    if lines.next().transpose()?.as_deref() != Some(HEADER) {
+        return Err(anyhow!("{} is not a scan checkpoint", path.display()));
+    }
+
+    let key = key(root);
+    let mut resumed = Resumed::default();
+    let mut current = false;
+    // Files after the last marker may be incomplete; they are walked again
+    let mut pending = Vec::new();
+    for line in lines {
+        let line = line?;
+        let (tag, value) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+        match tag {
+            "root" => {
+                current = value == key;
+                pending.clear();
+            }
+            "f" if current => pending.push(value.to_string()),
+            "@" if current => {
+                resumed.files.append(&mut pending);
+                resumed.last = Some(value.to_string());
+            }
+            "done" if current => {
+                resumed.files.append(&mut pending);
+                resumed.complete = true;
+            }
+            _ => {}
+        }
+    }
+    Ok(resumed)
+}
+
+/// Start `path` afresh, discarding any earlier progress
+pub fn reset(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("cannot remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Records one walk of a root
+pub struct Checkpoint {
+    out: BufWriter<File>,
+    marked: Instant,
+    /// Last file recorded
+    last: Option<String>,
+}
+
+impl Checkpoint {
+    pub fn open(path: &Path, root: &Path) -> Result<Checkpoint> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        let fresh = file.metadata()?.len() == 0;
+        let mut out = BufWriter::new(file);
+        if fresh {
+            writeln!(out, "{}", HEADER)?;
+        }
+        writeln!(out, "root {}", key(root))?;
+        Ok(Checkpoint {
+            out,
+            marked: Instant::now(),
+            last: None,
+        })
+    }
+
+    /// Record a file the walk found, marking progress every few seconds
+    pub fn file(&mut self, rel: &str) -> Result<()> {
+        writeln!(self.out, "f {}", rel)?;
+        if self.marked.elapsed() >= INTERVAL {
+            writeln!(self.out, "@ {}", rel)?;
+            self.out.flush()?;
+            self.marked = Instant::now();
+        }
+        self.last = Some(rel.to_string());
+        Ok(())
+    }
+
+    /// Mark every file recorded so far as durable, for a walk stopped early
+    pub fn pause(mut self) -> Result<()> {
+        if let Some(last) = &self.last {
+            writeln!(self.out, "@ {}", last)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        writeln!(self.out, "done")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Whether a resumed walk still has to visit `path`: it sorts after the
+/// last recorded file or leads to it
+pub fn pending(path: &Path, last: &Path) -> bool {
+    path > last || last.parent().is_some_and(|p| p.starts_with(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_marked_progress_is_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("scan");
+        let root = dir.path();
+        let other = dir.path().join("other");
+        fs::create_dir(&other).unwrap();
+        let text = format!(
+            "{}\nroot {}\nf a\nf b\n@ b\nf c\nroot {}\nf x\ndone\n",
+            HEADER,
+            key(root),
+            key(&other)
+        );
+        fs::write(&file, text).unwrap();
+        let resumed = load(&file, root).unwrap();
+        assert_eq!(resumed.files, ["a", "b"]);
+        assert_eq!(resumed.last.as_deref(), Some("b"));
+        assert!(!resumed.complete);
+        assert!(load(&file, &other).unwrap().complete);
+
+        reset(&file).unwrap();
+        assert!(load(&file, root).unwrap().files.is_empty());
+        let mut checkpoint = Checkpoint::open(&file, root).unwrap();
+        checkpoint.file("a").unwrap();
+        checkpoint.finish().unwrap();
+        let resumed = load(&file, root).unwrap();
+        assert_eq!((resumed.files.len(), resumed.complete), (1, true));
+        fs::write(&file, "something else\n").unwrap();
+        assert!(load(&file, root).is_err());
+    }
+
+    #[test]
+    fn pending_paths() {
+        let last = Path::new("src/m/x.rs");
+        assert!(pending(Path::new("src/n.rs"), last));
+        assert!(pending(Path::new("src"), last));
+        assert!(pending(Path::new("src/m"), last));
+        assert!(!pending(Path::new("src/a.rs"), last));
+        assert!(!pending(Path::new("src/m/x.rs"), last));
+    }
+
+    #[test]
+    fn paused_scans_resume_after_the_last_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("scan");
+        let mut checkpoint = Checkpoint::open(&file, dir.path()).unwrap();
+        checkpoint.file("a").unwrap();
+        checkpoint.file("b").unwrap();
+        checkpoint.pause().unwrap();
+        let resumed = load(&file, dir.path()).unwrap();
+        assert_eq!(resumed.files, ["a", "b"]);
+        assert_eq!(resumed.last.as_deref(), Some("b"));
+        assert!(!resumed.complete);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::cli::Tokenizer;
+use crate::template::TemplateFile;
+use crate::tokens;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Document-level metadata repeated on every chunk
+#[derive(Serialize)]
+struct DocumentMeta<'a> {
+    path: &'a str,
+    language: Option<&'static str>,
+    sha256: &'a str,
+    size: u64,
+    lines: usize,
+    chunks: usize,
+    commit: Option<&'a str>,
+}
+
+/// One JSON Lines record
+#[derive(Serialize)]
+struct Chunk<'a> {
+    id: String,
+    index: usize,
+    start_line: usize,
+    end_line: usize,
+    tokens: usize,
+    text: &'a str,
+    document: &'a DocumentMeta<'a>,
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Deterministic ID from path, content hash and chunk index
+///
+/// Unchanged files keep their IDs across runs, so ingestion jobs can
+/// upsert; any edit to a file changes the IDs of all its chunks.
+fn chunk_id(path: &str, sha256: &str, index: usize) -> String {
+    let key = format!("{}\0{}\0{}", path, sha256, index);
+    hex_sha256(key.as_bytes())[..32].to_owned()
+}
+
+/// ID from path and chunk text alone, for `--stable-ids`: a chunk keeps it
+/// while its text stays the same, whatever changes elsewhere in the file
+///
+/// `occurrence` tells apart identical chunks of one file.
+fn stable_chunk_id(path: &str, text: &str, occurrence: usize) -> String {
+    let key = format!("{}\0{}\0{}", path, hex_sha256(text.as_bytes()), occurrence);
+    hex_sha256(key.as_bytes())[..32].to_owned()
+}
+
+/// Split `content` on line boundaries into pieces of at most `max_tokens`
+///
+/// A single line longer than the limit becomes a chunk of its own.
+/// Returns (first line, last line, text) with 1-based line numbers.
+fn split(content: &str, max_tokens: usize, tokenizer: Tokenizer) -> Vec<(usize, usize, &str)> {
+    let mut out = Vec::new();
+    let (mut start, mut start_line, mut used) = (0, 1, 0);
+    let (mut offset, mut lines) = (0, 0);
+    for line in content.split_inclusive('\n') {
+        let cost = tokens::count(tokenizer, line);
+        if used > 0 && used + cost > max_tokens {
+            out.push((start_line, lines, &content[start..offset]));
+            start = offset;
+            start_line = lines + 1;
+            used = 0;
+        }
+        used += cost;
+        offset += line.len();
+        lines += 1;
+    }
+    if offset > start {
+        out.push((start_line, lines, &content[start..offset]));
+    }
+    out
+}
+
+/// Write every document's chunks to `path` as JSON Lines
+pub fn write(
+    path: &Path,
+    documents: &[TemplateFile],
+    max_tokens: usize,
+    tokenizer: Tokenizer,
+    stable_ids: bool,
+) -> Result<usize> {
+    let mut w = BufWriter::new(
+        File::create(path).with_context(|| format!("cannot create {}", path.display()))?,
+    );
+    let mut written = 0;
+    for doc in documents {
+        let sha256 = hex_sha256(doc.content.as_bytes());
+        let pieces = split(&doc.content, max_tokens, tokenizer);
+        let meta = DocumentMeta {
+            path: &doc.path,
+            language: doc.language,
+            sha256: &sha256,
+            size: doc.size,
+            lines: doc.lines,
+            chunks: pieces.len(),
+            commit: doc.commit.as_deref(),
+        };
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for (index, (start_line, end_line, text)) in pieces.into_iter().enumerate() {
+            let id = if stable_ids {
+                let occurrence = seen.entry(text).or_default();
+                *occurrence += 1;
+                stable_chunk_id(&doc.path, text, *occurrence)
+            } else {
+                chunk_id(&doc.path, &sha256, index)
+            };
+            let chunk = Chunk {
+                id,
+                index,
+                start_line,
+                end_line,
+                tokens: tokens::count(tokenizer, text),
+                text,
+                document: &meta,
+            };
+            serde_json::to_writer(&mut w, &chunk)?;
+            writeln!(w)?;
+            written += 1;
+        }
+    }
+    w.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_and_ids() {
+        let text = "aaaa\nbbbb\ncccc\ndddddddddddddddddddd\n";
+        assert_eq!(
+            split(text, 4, Tokenizer::Chars),
+            [(1, 2, "aaaa\nbbbb\n"), (3, 3, "cccc\n"), (4, 4, "dddddddddddddddddddd\n")]
+        );
+        assert_eq!(split("no newline", 100, Tokenizer::Chars), [(1, 1, "no newline")]);
+        assert!(split("", 100, Tokenizer::Chars).is_empty());
+        let sha = hex_sha256(b"x");
+        assert_eq!(chunk_id("a.rs", &sha, 0), chunk_id("a.rs", &sha, 0));
+        assert_eq!(chunk_id("a.rs", &sha, 0).len(), 32);
+        assert_ne!(chunk_id("a.rs", &sha, 0), chunk_id("a.rs", &sha, 1));
+        assert_ne!(chunk_id("a.rs", &sha, 0), chunk_id("b.rs", &sha, 0));
+    }
+
+    #[test]
+    fn stable_ids_follow_the_text() {
+        let id = stable_chunk_id("a.rs", "fn f() {}\n", 1);
+        assert_eq!(id.len(), 32);
+        assert_eq!(id, stable_chunk_id("a.rs", "fn f() {}\n", 1));
+        assert_ne!(id, stable_chunk_id("a.rs", "fn f() {}\n", 2));
+        assert_ne!(id, stable_chunk_id("b.rs", "fn f() {}\n", 1));
+        assert_ne!(id, stable_chunk_id("a.rs", "fn g() {}\n", 1));
+    }
+}
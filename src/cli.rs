@@ -1,29 +1,404 @@
-use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
+use crate::settings::{Diagnostics, Settings};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 /// Header styles
-#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum HeaderStyle {
     Plain,
     Hash,
     Underline,
 }
 
+/// Layouts of the merged output
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum OutputFormat {
+    /// Files separated by --header-style headers
+    Text,
+    /// A markdown heading per file and its contents in a fenced code block
+    Markdown,
+    /// One JSON object: a manifest and an array of file records
+    Json,
+    /// JSON Lines: a manifest record, then a record per file
+    Jsonl,
+}
+
+/// Character encodings of written outputs
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum OutputEncoding {
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    /// UTF-16, little-endian, with a byte order mark
+    Utf16le,
+}
+
+/// Whether file lines that look like headers are escaped
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Delimiters {
+    /// Put a `\` before them, so the output reads back unambiguously
+    Safe,
+    /// Write every line as it is
+    Plain,
+}
+
+/// How progress is reported on stderr
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ProgressFormat {
+    /// A progress bar while writing
+    Bar,
+    /// Newline-delimited JSON events, for programs embedding treemerge
+    Json,
+}
+
+/// What to do with Git LFS pointer files
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum LfsMode {
+    /// Include the pointer, marked as such in the header
+    Note,
+    /// Leave pointer files out
+    Skip,
+}
+
+/// How header paths name the repository they belong to, with several
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum RootLabel {
+    /// As a leading directory: `backend/src/api.rs`
+    Dir,
+    /// In brackets, apart from the path: `[backend] src/api.rs`
+    Bracket,
+}
+
+/// What `--scan-secrets` does about likely credentials in the selection
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum SecretScan {
+    /// List them on stderr and write the output anyway
+    Warn,
+    /// List them and write nothing, exiting with an error
+    Block,
+}
+
+/// Broad kinds of files for `--only`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum FileKind {
+    /// Source in a programming language (Rust, Python, shell, HTML, ...)
+    Code,
+    /// Prose: markdown, reStructuredText, AsciiDoc, LaTeX, plain text, README and the like
+    Docs,
+    /// Settings and data: TOML, YAML, JSON, INI, XML
+    Config,
+}
+
+/// What to do with git submodules
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum SubmoduleMode {
+    /// Merge checked-out submodules like any other directory
+    Include,
+    /// Leave submodules out
+    Skip,
+}
+
+/// Token estimators
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tokenizer {
+    /// Word pieces and symbols, close to BPE counts for code
+    Approx,
+    /// One token per four characters
+    Chars,
+}
+
+/// Orderings for file listings
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ListSort {
+    Path,
+    Size,
+    Lines,
+    Tokens,
+}
+
+/// How `--budget-tokens` shrinks a selection that is too large
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum TrimStrategy {
+    /// Drop the files with the most tokens first
+    DropLargest,
+    /// Drop the least important files first (READMEs and entry points last)
+    DropLowestPriority,
+    /// Replace the largest files with outlines, dropping only if that is not enough
+    OutlineFallback,
+}
+
+/// What `--max-file-tokens` does with a file over the limit
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Overflow {
+    /// Leave it out
+    Skip,
+    /// Keep its first lines, as many as fit
+    Truncate,
+    /// Replace it with its outline, truncated if still too long
+    Outline,
+}
+
+/// Which files `--max-files` keeps
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum MaxFilesOrder {
+    /// The most important files (READMEs, manifests, entry points, sources)
+    Priority,
+    /// The smallest files
+    Smallest,
+    /// The first files in path order
+    Path,
+}
+
+/// How `--sample` spreads its picks
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum SampleStrata {
+    /// Uniformly over all files
+    Random,
+    /// Proportionally over top-level directories, each getting at least one
+    Directory,
+    /// Proportionally over languages, each getting at least one
+    Language,
+}
+
+/// `--sample N[,SEED]`
+#[derive(Copy, Clone, Debug)]
+pub struct Sample {
+    pub count: usize,
+    pub seed: u64,
+}
+
+/// Groups of built-in excludes
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ExcludeCategory {
+    /// .git/, .svn/, .hg/
+    Vcs,
+    /// target/, dist/, build/, out/
+    Build,
+    /// __pycache__/, .venv/, node_modules/, editor settings, ...
+    Caches,
+    /// _site/, _book/, docs/_build/
+    DocsOutput,
+    /// LICENSE, COPYING, NOTICE
+    Boilerplate,
+    /// *.lock, package-lock.json, pnpm-lock.yaml, ...
+    Lockfiles,
+    /// *.pyc, *.o, *.so, *.dll, *.exe, ...
+    Binaries,
+    /// .env, *.pem, *.key, id_rsa, credentials.json, ...
+    Secrets,
+}
+
+/// When to color terminal reports
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum ColorChoice {
+    /// When stdout is a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+/// HTTP method for `--upload-url`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum UploadMethod {
+    Post,
+    Put,
+}
+
+/// Output formats for `--dep-graph`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum DepGraphFormat {
+    /// Each file followed by the files it imports
+    List,
+    /// Graphviz digraph
+    Dot,
+}
+
+/// Order of the files in the output
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum SortOrder {
+    /// Byte-wise by path
+    Path,
+    /// Largest first
+    Size,
+    /// Last modified first, across the whole tree
+    #[value(alias = "by-mtime")]
+    Mtime,
+    /// By extension, then by path
+    Ext,
+    /// Each directory's files, then its subdirectories the same way
+    #[value(alias = "dfs")]
+    DepthFirst,
+    /// Shallowest first, then by path
+    Bfs,
+}
+
+/// What `--toc` shows beside each file
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum TocDetail {
+    /// Names only
+    Names,
+    /// Line counts
+    Lines,
+    /// Sizes
+    Sizes,
+    /// Line counts and sizes
+    Full,
+}
+
+/// Formats of the `--per-file-stats` section
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum PerFileStatsFormat {
+    /// Aligned columns with a total row
+    Table,
+    /// A JSON array with one object per file
+    Json,
+}
+
+/// Whether and how `--timestamp` records when the output was made
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum TimestampMode {
+    /// No timestamp, so identical inputs give identical outputs
+    None,
+    /// ISO 8601 in UTC
+    Utc,
+    /// ISO 8601 with the local offset
+    Local,
+}
+
+/// Commands other than merging
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Show every filter rule a path hits and whether it would be merged
+    Why {
+        /// Root directory, as given to a merge
+        dir: PathBuf,
+        /// File to check, relative to DIR or as a path
+        path: PathBuf,
+        /// Selection options of the merge to reproduce (-i, -x, --ext, ...)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "OPTIONS")]
+        options: Vec<String>,
+    },
+    /// Propose a .treemergeignore for a tree: generated, large and near-binary files
+    SuggestIgnore {
+        /// Files above this size are proposed (e.g. 1MB, 500KB)
+        #[arg(long = "large", value_name = "SIZE", value_parser = parse_size, default_value = "1MB")]
+        large: u64,
+        /// Root directory, as given to a merge
+        dir: PathBuf,
+        /// Selection options of the merge to reproduce (-i, -x, --ext, ...)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "OPTIONS")]
+        options: Vec<String>,
+    },
+    /// Replace this binary with the latest GitHub release, after checking its checksum
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long = "check")]
+        check: bool,
+    },
+    /// Recreate the files of a merge output, split parts included
+    Unmerge {
+        /// Output of an earlier merge (the first part, when it was split)
+        file: PathBuf,
+        /// Directory the files are written under
+        #[arg(long = "out-dir", value_name = "DIR")]
+        out_dir: PathBuf,
+        /// Overwrite files that already exist
+        #[arg(long = "force")]
+        force: bool,
+        /// Manifest the merge wrote with --manifest: files it lists unchanged get their mode and mtime back
+        #[arg(long = "manifest", value_name = "FILE")]
+        manifest: Option<PathBuf>,
+    },
+    /// Run the merge jobs of a TOML file one after the other, then report on all of them
+    Batch {
+        /// Jobs file: [[job]] tables with root, output, profile and flags
+        file: PathBuf,
+        /// Also write the report as JSON to FILE
+        #[arg(long = "report", value_name = "FILE")]
+        report: Option<PathBuf>,
+        /// Stop at the first job that fails
+        #[arg(long = "fail-fast")]
+        fail_fast: bool,
+    },
+    /// Inspect the user configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+/// `treemerge config` actions
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the JSON Schema of config.toml, for editor completion and validation
+    Schema,
+    /// Print where the config file is read from
+    Path,
+}
+
 /// Command-line arguments
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Concatenate all text files in a directory tree.")]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+// Options from the user config come first; the command line overrides them
+#[command(args_override_self = true)]
 pub struct Args {
-    /// Root directory to process (must be a directory)
-    pub path: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Root directory to process, or a git URL / github:owner/repo to clone
+    #[arg(required_unless_present_any = ["repo", "workspaces"])]
+    pub path: Option<PathBuf>,
+
+    /// Merge several repositories (directory or URL, optionally NAME=...) into one output
+    #[arg(long = "repo", value_name = "PATH_OR_URL", conflicts_with = "path")]
+    pub repo: Vec<String>,
 
-    /// Output file name; defaults to <dirname>.txt
+    /// Read the repositories to merge from a workspaces.toml file
+    #[arg(long = "workspaces", value_name = "FILE", conflicts_with = "path")]
+    pub workspaces: Option<PathBuf>,
+
+    /// With several roots, write one output per root (<name>.txt, or <stem>-<name>.<ext> with -o)
+    #[arg(long = "output-per-root", conflicts_with_all = ["manifest", "report_skipped", "chunks"])]
+    pub output_per_root: bool,
+
+    /// With several roots, prefix header paths with the root's name as a directory or in brackets
+    #[arg(long = "root-label", value_enum, default_value = "dir")]
+    pub root_label: RootLabel,
+
+    /// Output file name (- for stdout); defaults to <dirname>.txt (joined names for several roots)
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Glob patterns to include (repeatable)
+    /// Write the merge to stdout, like --output - (over any --output, e.g. from treemerge.toml)
+    #[arg(long = "stdout")]
+    pub stdout: bool,
+
+    /// Keep running and merge again whenever files under the root change
+    #[arg(
+        long = "watch",
+        conflicts_with_all = ["stdout", "dry_run", "count_tokens", "explain", "open", "pager", "upload_url"]
+    )]
+    pub watch: bool,
+
+    /// Quiet time after a change before --watch merges again
+    #[arg(long = "watch-debounce", value_name = "MS", default_value_t = 300, requires = "watch")]
+    pub watch_debounce: u64,
+
+    /// Glob patterns to include (repeatable); GLOB:START-END keeps only those lines
     #[arg(short = 'i', long = "include")]
     pub include: Vec<String>,
 
+    /// Include the GLOB:START-END (or plain GLOB) specs listed in FILE, one per line
+    #[arg(long = "ranges-from", value_name = "FILE")]
+    pub ranges_from: Option<PathBuf>,
+
     /// Glob patterns to exclude (repeatable)
     #[arg(short = 'x', long = "exclude")]
     pub exclude: Vec<String>,
@@ -32,31 +407,766 @@ pub struct Args {
     #[arg(short = 'e', long = "ext")]
     pub ext: Vec<String>,
 
+    /// Only include files of this kind, judged by language (repeatable)
+    #[arg(long = "only", value_name = "KIND", value_enum)]
+    pub only: Vec<FileKind>,
+
     /// Disable default excludes
     #[arg(long = "all-files")]
     pub all_files: bool,
 
+    /// Keep the files of this built-in exclude category (repeatable)
+    #[arg(long = "no-default-exclude", value_name = "CATEGORY", value_enum)]
+    pub no_default_exclude: Vec<ExcludeCategory>,
+
+    /// Only apply these built-in exclude categories (repeatable)
+    #[arg(long = "default-exclude-only", value_name = "CATEGORY", value_enum)]
+    pub default_exclude_only: Vec<ExcludeCategory>,
+
+    /// Honor .gitignore and .ignore files and the global git excludes (the default)
+    #[arg(long = "use-gitignore", overrides_with = "no_gitignore")]
+    pub use_gitignore: bool,
+
+    /// Do not honor .gitignore or .ignore files (nested, .git/info/exclude, global excludes)
+    #[arg(long = "no-gitignore", overrides_with = "use_gitignore")]
+    pub no_gitignore: bool,
+
+    /// Handling of Git LFS pointer files
+    #[arg(long = "lfs", value_enum, default_value = "note")]
+    pub lfs: LfsMode,
+
+    /// Handling of git submodules
+    #[arg(long = "submodules", value_enum, default_value = "include")]
+    pub submodules: SubmoduleMode,
+
+    /// Check out submodules missing from the working tree (shallow) before merging
+    #[arg(long = "fetch-submodules")]
+    pub fetch_submodules: bool,
+
+    /// Fetch the real content of Git LFS pointers (via `git lfs smudge`) when it is text
+    #[arg(long = "lfs-smudge")]
+    pub lfs_smudge: bool,
+
     /// Line count after which to split output (never splits inside a file)
     #[arg(long = "split-every")]
     pub split_every: Option<usize>,
 
+    /// Split output so no part exceeds N estimated tokens (never splits inside a file)
+    #[arg(long = "max-tokens", value_name = "N", conflicts_with = "split_every")]
+    pub max_tokens: Option<usize>,
+
     /// Header style for file separators
     #[arg(long = "header-style", value_enum, default_value = "hash")]
     pub header_style: HeaderStyle,
 
+    /// When the output exists, list the files added, removed and modified since, before overwriting it
+    #[arg(long = "diff-previous", conflicts_with_all = ["template", "prompt", "explode", "output_per_root"])]
+    pub diff_previous: bool,
+
+    /// Output layout; markdown fences each file with its language and ignores --header-style, json and jsonl write a record per file
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        conflicts_with_all = ["template", "prompt", "explode"]
+    )]
+    pub format: OutputFormat,
+
+    /// Escaping of file lines that look like headers (text format; markdown fences are always unambiguous)
+    #[arg(long = "delimiters", value_enum, default_value_t = Delimiters::Safe)]
+    pub delimiters: Delimiters,
+
+    /// Encoding of the written output, for tools that require a BOM or UTF-16
+    #[arg(long = "output-encoding", value_enum, default_value_t = OutputEncoding::Utf8)]
+    pub output_encoding: OutputEncoding,
+
+    /// Emit a preamble with remote, branch, commit and dirty state when the root is a git repo
+    #[arg(long = "git-preamble")]
+    pub git_preamble: bool,
+
+    /// Add each file's last commit hash, author and date to its header
+    #[arg(long = "git-meta")]
+    pub git_meta: bool,
+
+    /// Add a blame summary (top authors, line age distribution) to each header
+    #[arg(long = "blame-summary")]
+    pub blame_summary: bool,
+
+    /// Mark files with uncommitted changes in their headers ([modified], [untracked], ...)
+    #[arg(long = "git-status")]
+    pub git_status: bool,
+
+    /// Emit each changed file's unified diff for a revision range (A..B, A...B, or REV vs working tree)
+    #[arg(long = "diff", value_name = "REV_RANGE")]
+    pub diff: Option<String>,
+
+    /// With --diff, also include the full post-image of changed files up to this many lines
+    #[arg(long = "diff-full-under", value_name = "LINES", requires = "diff")]
+    pub diff_full_under: Option<usize>,
+
+    /// Include files changed since REV in full, plus outlines of the files they import
+    #[arg(long = "changed-with-context", value_name = "REV", conflicts_with = "diff")]
+    pub changed_with_context: Option<String>,
+
+    /// Write a JSON manifest (path, size, sha256, mtime, commit) of every included file
+    #[arg(long = "manifest", value_name = "FILE")]
+    pub manifest: Option<PathBuf>,
+
+    /// Sign the manifest with this SSH private key (writes <manifest>.sig)
+    #[arg(long = "manifest-sign-key", value_name = "KEY", requires = "manifest")]
+    pub manifest_sign_key: Option<PathBuf>,
+
+    /// Record extended attributes matching this glob (e.g. 'user.*'), owner and link count in the manifest (repeatable)
+    #[cfg(all(unix, feature = "unix-filters"))]
+    #[arg(long = "xattrs", value_name = "GLOB", requires = "manifest")]
+    pub xattrs: Vec<String>,
+
+    /// Write every skipped file and the reason (filter, sample, budget, read error) to FILE
+    #[arg(long = "report-skipped", value_name = "FILE")]
+    pub report_skipped: Option<PathBuf>,
+
+    /// Append a table of direct dependencies from Cargo.toml / package.json / pyproject.toml
+    #[arg(long = "deps-summary")]
+    pub deps_summary: bool,
+
+    /// Run this shell command in the root and append its stdout as a section (repeatable)
+    #[arg(long = "capture", value_name = "CMD")]
+    pub capture: Vec<String>,
+
+    /// Order of the merged files: path, size (largest first), mtime (newest first), ext, depth-first or bfs
+    #[arg(long = "sort", visible_alias = "walk-order", value_name = "ORDER", value_enum, default_value_t = SortOrder::Path)]
+    pub sort: SortOrder,
+
+    /// Put the files matching the paths or globs listed in FILE first, in the order listed
+    #[arg(long = "order-file", value_name = "FILE")]
+    pub order_file: Option<PathBuf>,
+
+    /// Put conventional entry points (main.rs, index.ts, pom.xml, ...) first and flag them
+    #[arg(long = "entry-points")]
+    pub entry_points: bool,
+
+    /// Append the import graph between included files (list or dot)
+    #[arg(
+        long = "dep-graph",
+        value_name = "FORMAT",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "list"
+    )]
+    pub dep_graph: Option<DepGraphFormat>,
+
+    /// Append lines, words, characters and tokens of every included file (table or json)
+    #[arg(
+        long = "per-file-stats",
+        value_name = "FORMAT",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "table"
+    )]
+    pub per_file_stats: Option<PerFileStatsFormat>,
+
+    /// Start with a tree of the included files, annotated with lines, sizes or both (full)
+    #[arg(
+        long = "toc",
+        value_name = "DETAIL",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "names",
+        conflicts_with_all = ["template", "prompt", "explode"]
+    )]
+    pub toc: Option<TocDetail>,
+
+    /// Before the first file of each directory, a line totalling its files, lines and main languages
+    #[arg(long = "dir-summaries", conflicts_with_all = ["template", "prompt", "explode"])]
+    pub dir_summaries: bool,
+
+    /// Put the contents of FILE (e.g. markdown) at the top as an Instructions section
+    #[arg(long = "instructions", value_name = "FILE")]
+    pub instructions: Option<PathBuf>,
+
+    /// Wrap the output in a prompt scaffold: code-review, onboarding, bug-hunt or a user prompt
+    #[arg(
+        long = "prompt",
+        value_name = "NAME",
+        conflicts_with_all = ["template", "split_every", "max_tokens"]
+    )]
+    pub prompt: Option<String>,
+
+    /// Also write the selection as JSON Lines chunks with stable IDs for embedding
+    #[arg(long = "chunks", value_name = "FILE")]
+    pub chunks: Option<PathBuf>,
+
+    /// Name split parts and --chunks IDs after their content, so unchanged ones survive reruns
+    #[arg(long = "stable-ids")]
+    pub stable_ids: bool,
+
+    /// Maximum tokens per chunk for --chunks
+    #[arg(
+        long = "chunk-tokens",
+        value_name = "N",
+        default_value_t = 512,
+        requires = "chunks"
+    )]
+    pub chunk_tokens: usize,
+
+    /// Write one markdown document per file under DIR, mirroring the tree, instead of one output
+    #[arg(
+        long = "explode",
+        value_name = "DIR",
+        conflicts_with_all = ["output", "template", "prompt", "split_every", "max_tokens"]
+    )]
+    pub explode: Option<PathBuf>,
+
+    /// Render the whole output through a Jinja-style template instead of headers
+    #[arg(long = "template", value_name = "FILE", conflicts_with_all = ["split_every", "max_tokens"])]
+    pub template: Option<PathBuf>,
+
+    /// Send the output (each part, when splitting) to URL and print the returned links
+    #[arg(long = "upload-url", value_name = "URL")]
+    pub upload_url: Option<String>,
+
+    /// HTTP method for --upload-url
+    #[arg(
+        long = "upload-method",
+        value_enum,
+        default_value_t = UploadMethod::Post,
+        requires = "upload_url"
+    )]
+    pub upload_method: UploadMethod,
+
+    /// Extra request header for --upload-url, as 'Name: value' (repeatable)
+    #[arg(long = "upload-header", value_name = "HEADER", requires = "upload_url")]
+    pub upload_header: Vec<String>,
+
+    /// Bearer token for --upload-url
+    #[arg(
+        long = "upload-token",
+        value_name = "TOKEN",
+        env = "TREEMERGE_UPLOAD_TOKEN",
+        hide_env_values = true
+    )]
+    pub upload_token: Option<String>,
+
+    /// Open the result in $VISUAL/$EDITOR (HTML in the default viewer) after merging
+    #[arg(long = "open", conflicts_with_all = ["dry_run", "count_tokens"])]
+    pub open: bool,
+
+    /// Page through the result with $PAGER (default less) when on a terminal
+    #[arg(long = "pager", conflicts_with_all = ["dry_run", "count_tokens"])]
+    pub pager: bool,
+
+    /// Color the dry-run and --explain listings
+    #[arg(long = "color", value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
     /// Dry-run mode (no files written)
     #[arg(long = "dry-run")]
     pub dry_run: bool,
 
+    /// Progress on stderr: a bar, or JSON lines (scanned, filtered, file, part, done) for GUIs
+    #[arg(long = "progress-format", value_enum, default_value_t = ProgressFormat::Bar)]
+    pub progress_format: ProgressFormat,
+
+    /// Like --dry-run, but only report estimated tokens per file and in total
+    #[arg(long = "count-tokens")]
+    pub count_tokens: bool,
+
+    /// Like --dry-run, but list every file with the reason it is merged or not
+    #[arg(long = "explain", conflicts_with_all = ["diff", "changed_with_context"])]
+    pub explain: bool,
+
+    /// Estimated output size above which to ask for confirmation (e.g. 500MB, 2G; 0 disables)
+    #[arg(long = "max-output-size", value_parser = parse_size, default_value = "500MB")]
+    pub max_output_size: u64,
+
+    /// Before scanning, warn about top-level directories with more than SIZE to merge (default 100MB)
+    #[arg(
+        long = "probe-dirs",
+        value_name = "SIZE",
+        value_parser = parse_size,
+        num_args = 0..=1,
+        default_missing_value = "100MB"
+    )]
+    pub probe_dirs: Option<u64>,
+
+    /// Always leave out the paths listed in FILE (one per line, e.g. a --report-skipped file)
+    #[arg(long = "skip-from", value_name = "FILE")]
+    pub skip_from: Option<PathBuf>,
+
+    /// Mask email addresses, phone numbers and IP addresses in the merged content
+    #[arg(long = "redact-pii")]
+    pub redact_pii: bool,
+
+    /// Replace likely secrets (API tokens, private keys, high-entropy strings) with [REDACTED]
+    #[arg(long = "redact", overrides_with = "no_redact")]
+    pub redact: bool,
+
+    /// Undo --redact, e.g. one from the configuration
+    #[arg(long = "no-redact", overrides_with = "redact")]
+    pub no_redact: bool,
+
+    /// Look for credentials in the selection before writing: warn, or block the output
+    #[arg(long = "scan-secrets", value_name = "MODE", value_enum)]
+    pub scan_secrets: Option<SecretScan>,
+
     /// Skip confirmation prompts
     #[arg(long = "no-confirm")]
     pub no_confirm: bool,
 
+    /// Show paths with the platform's separator instead of `/`
+    #[arg(long = "native-separators")]
+    pub native_separators: bool,
+
+    /// Only include files owned by this user (name or numeric uid)
+    #[cfg(all(unix, feature = "unix-filters"))]
+    #[arg(long = "owned-by", value_name = "USER")]
+    pub owned_by: Option<String>,
+
+    /// Only include files whose mode has all bits of this octal mask (e.g. 004 = world-readable)
+    #[cfg(all(unix, feature = "unix-filters"))]
+    #[arg(long = "perm", value_name = "MASK", value_parser = parse_octal)]
+    pub perm: Option<u32>,
+
     /// Follow symlinked directories
     #[arg(long = "follow-symlinks")]
     pub follow_symlinks: bool,
 
+    /// Add a JSON line after the output marker with the version, options, time, roots and commits
+    #[arg(long = "metadata")]
+    pub metadata: bool,
+
+    /// Add a generation time after the output marker (SOURCE_DATE_EPOCH pins it)
+    #[arg(long = "timestamp", value_enum, default_value = "none")]
+    pub timestamp: TimestampMode,
+
+    /// Merge files that are themselves treemerge outputs instead of skipping them
+    #[arg(long = "include-merged")]
+    pub include_merged: bool,
+
+    /// Note in headers which of several same-named files (mod.rs, index.ts) each one is
+    #[arg(long = "basename-hints")]
+    pub basename_hints: bool,
+
+    /// Emit symbolic links as empty entries naming their target instead of skipping them
+    #[arg(long = "symlink-stubs", conflicts_with = "follow_symlinks")]
+    pub symlink_stubs: bool,
+
+    /// Record walk progress in FILE so an interrupted scan can be resumed
+    #[arg(long = "scan-checkpoint", value_name = "FILE")]
+    pub scan_checkpoint: Option<PathBuf>,
+
+    /// Continue the walk recorded in --scan-checkpoint instead of starting over
+    #[arg(long = "resume-scan", requires = "scan_checkpoint")]
+    pub resume_scan: bool,
+
+    /// Merge the files listed in FILE (- for stdin), one per line, instead of walking the tree
+    #[arg(long = "files-from", value_name = "FILE", conflicts_with = "scan_checkpoint")]
+    pub files_from: Option<PathBuf>,
+
+    /// Only merge this workspace package (cargo, npm/pnpm/yarn, Python) and its in-repo dependencies
+    #[arg(long = "package", value_name = "NAME")]
+    pub package: Option<String>,
+
+    /// Tokenizer used for token counts
+    #[arg(long = "tokenizer", value_enum, default_value_t = Tokenizer::Approx)]
+    pub tokenizer: Tokenizer,
+
+    /// Order of the dry-run listing by path, size, lines or tokens (default: merge order)
+    #[arg(long = "list-sort", value_name = "KEY", value_enum)]
+    pub list_sort: Option<ListSort>,
+
+    /// Project overview: the top-level README and configs in full, outlines of source files, and the tree
+    #[arg(long = "digest", conflicts_with_all = ["diff", "changed_with_context"])]
+    pub digest: bool,
+
+    /// Replace files above --summarize-threshold with the output of CMD ({} is the path)
+    #[arg(long = "summarize-large", value_name = "CMD")]
+    pub summarize_large: Option<String>,
+
+    /// Size above which --summarize-large applies
+    #[arg(
+        long = "summarize-threshold",
+        value_name = "SIZE",
+        value_parser = parse_size,
+        default_value = "100KB",
+        requires = "summarize_large"
+    )]
+    pub summarize_threshold: u64,
+
+    /// Trim the selection until its estimated token count fits in N
+    #[arg(long = "budget-tokens", value_name = "N")]
+    pub budget_tokens: Option<usize>,
+
+    /// How to trim the selection for --budget-tokens
+    #[arg(
+        long = "trim-strategy",
+        value_enum,
+        default_value_t = TrimStrategy::DropLowestPriority,
+        requires = "budget_tokens"
+    )]
+    pub trim_strategy: TrimStrategy,
+
+    /// Leave out (or shrink, per --overflow) any file of more than N estimated tokens
+    #[arg(long = "max-file-tokens", value_name = "N")]
+    pub max_file_tokens: Option<usize>,
+
+    /// What to do with a file over --max-file-tokens: skip, truncate or outline
+    #[arg(
+        long = "overflow",
+        value_enum,
+        default_value_t = Overflow::Skip,
+        requires = "max_file_tokens"
+    )]
+    pub overflow: Overflow,
+
+    /// Merge at most N files, keeping the first N by --max-files-by and reporting the rest
+    #[arg(long = "max-files", value_name = "N")]
+    pub max_files: Option<usize>,
+
+    /// Which files --max-files keeps
+    #[arg(
+        long = "max-files-by",
+        value_enum,
+        default_value_t = MaxFilesOrder::Priority,
+        requires = "max_files"
+    )]
+    pub max_files_by: MaxFilesOrder,
+
+    /// Merge a reproducible sample of N files; another SEED picks another sample
+    #[arg(long = "sample", value_name = "N[,SEED]", value_parser = parse_sample)]
+    pub sample: Option<Sample>,
+
+    /// How --sample spreads its picks over the tree
+    #[arg(
+        long = "sample-by",
+        value_enum,
+        default_value_t = SampleStrata::Random,
+        requires = "sample"
+    )]
+    pub sample_by: SampleStrata,
+
+    /// Report size and line histograms and percentiles of the selection
+    #[arg(long = "stats")]
+    pub stats: bool,
+
+    /// In reports, also list the N largest files by bytes, lines and tokens
+    #[arg(long = "top", value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Clone remote inputs with this history depth (e.g. 1 for a shallow clone)
+    #[arg(long = "depth", value_name = "N")]
+    pub depth: Option<u32>,
+
+    /// Only fetch and check out this path of a remote input (repeatable)
+    #[arg(long = "remote-path", value_name = "PATH")]
+    pub remote_path: Vec<String>,
+
+    /// Cap reads of input files at this many MB per second
+    #[arg(long = "io-limit", value_name = "MB/S", value_parser = parse_rate)]
+    pub io_limit: Option<f64>,
+
+    /// Run at the lowest I/O (and CPU) priority the platform offers
+    #[arg(long = "nice-io")]
+    pub nice_io: bool,
+
+    /// Times to retry opening a file locked by another process before skipping it
+    #[arg(long = "lock-retries", value_name = "N", default_value_t = 3)]
+    pub lock_retries: u32,
+
+    /// Delay between retries on locked files, in milliseconds
+    #[arg(long = "lock-retry-delay", value_name = "MS", default_value_t = 250)]
+    pub lock_retry_delay: u64,
+
+    /// Times to retry a transient I/O error (ESTALE, EIO, timeouts) before skipping the file
+    #[arg(long = "io-retries", value_name = "N", default_value_t = 3)]
+    pub io_retries: u32,
+
+    /// Delay before the first retry of a transient error, in milliseconds (doubles each time)
+    #[arg(long = "io-retry-delay", value_name = "MS", default_value_t = 200)]
+    pub io_retry_delay: u64,
+
+    /// Ignore the user config (~/.config/treemerge/config.toml) and the project's treemerge.toml
+    #[arg(long = "no-config")]
+    pub no_config: bool,
+
+    /// Project config to use instead of DIR/treemerge.toml
+    #[arg(long = "config", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Let DIR/treemerge.toml set any option, including ones that run commands or upload
+    #[arg(long = "trust-project")]
+    pub trust_project: bool,
+
+    /// Apply the [profile.NAME] table of the project config
+    #[arg(long = "profile", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Report how many files each glob pattern matched, flagging patterns that matched none
+    #[arg(long = "debug-globs")]
+    pub debug_globs: bool,
+
     /// Verbose logging
     #[arg(long = "verbose")]
     pub verbose: bool,
 }
+
+impl From<Args> for Settings {
+    fn from(args: Args) -> Settings {
+        Settings {
+            path: args.path,
+            repo: args.repo,
+            workspaces: args.workspaces,
+            output_per_root: args.output_per_root,
+            root_label: args.root_label,
+            output: args.output,
+            stdout: args.stdout,
+            include: args.include,
+            ranges_from: args.ranges_from,
+            exclude: args.exclude,
+            ext: args.ext,
+            only: args.only,
+            all_files: args.all_files,
+            no_default_exclude: args.no_default_exclude,
+            default_exclude_only: args.default_exclude_only,
+            no_gitignore: args.no_gitignore,
+            lfs: args.lfs,
+            submodules: args.submodules,
+            fetch_submodules: args.fetch_submodules,
+            lfs_smudge: args.lfs_smudge,
+            split_every: args.split_every,
+            max_tokens: args.max_tokens,
+            header_style: args.header_style,
+            diff_previous: args.diff_previous,
+            format: args.format,
+            delimiters: args.delimiters,
+            output_encoding: args.output_encoding,
+            git_preamble: args.git_preamble,
+            git_meta: args.git_meta,
+            blame_summary: args.blame_summary,
+            git_status: args.git_status,
+            diff: args.diff,
+            diff_full_under: args.diff_full_under,
+            changed_with_context: args.changed_with_context,
+            manifest: args.manifest,
+            manifest_sign_key: args.manifest_sign_key,
+            xattrs: args.xattrs,
+            report_skipped: args.report_skipped,
+            deps_summary: args.deps_summary,
+            capture: args.capture,
+            sort: args.sort,
+            order_file: args.order_file,
+            entry_points: args.entry_points,
+            dep_graph: args.dep_graph,
+            per_file_stats: args.per_file_stats,
+            toc: args.toc,
+            dir_summaries: args.dir_summaries,
+            instructions: args.instructions,
+            prompt: args.prompt,
+            chunks: args.chunks,
+            stable_ids: args.stable_ids,
+            chunk_tokens: args.chunk_tokens,
+            explode: args.explode,
+            template: args.template,
+            upload_url: args.upload_url,
+            upload_method: args.upload_method,
+            upload_header: args.upload_header,
+            upload_token: args.upload_token,
+            open: args.open,
+            pager: args.pager,
+            color: args.color,
+            dry_run: args.dry_run,
+            progress_format: args.progress_format,
+            count_tokens: args.count_tokens,
+            explain: args.explain,
+            max_output_size: args.max_output_size,
+            probe_dirs: args.probe_dirs,
+            skip_from: args.skip_from,
+            redact_pii: args.redact_pii,
+            redact: args.redact,
+            scan_secrets: args.scan_secrets,
+            no_confirm: args.no_confirm,
+            native_separators: args.native_separators,
+            owned_by: args.owned_by,
+            perm: args.perm,
+            follow_symlinks: args.follow_symlinks,
+            metadata: args.metadata,
+            timestamp: args.timestamp,
+            include_merged: args.include_merged,
+            basename_hints: args.basename_hints,
+            symlink_stubs: args.symlink_stubs,
+            scan_checkpoint: args.scan_checkpoint,
+            resume_scan: args.resume_scan,
+            files_from: args.files_from,
+            package: args.package,
+            tokenizer: args.tokenizer,
+            list_sort: args.list_sort,
+            digest: args.digest,
+            summarize_large: args.summarize_large,
+            summarize_threshold: args.summarize_threshold,
+            budget_tokens: args.budget_tokens,
+            trim_strategy: args.trim_strategy,
+            max_file_tokens: args.max_file_tokens,
+            overflow: args.overflow,
+            max_files: args.max_files,
+            max_files_by: args.max_files_by,
+            sample: args.sample,
+            sample_by: args.sample_by,
+            stats: args.stats,
+            top: args.top,
+            depth: args.depth,
+            remote_path: args.remote_path,
+            io_limit: args.io_limit,
+            nice_io: args.nice_io,
+            lock_retries: args.lock_retries,
+            lock_retry_delay: args.lock_retry_delay,
+            io_retries: args.io_retries,
+            io_retry_delay: args.io_retry_delay,
+            debug_globs: args.debug_globs,
+            verbose: args.verbose,
+            diagnostics: Diagnostics::default(),
+        }
+    }
+}
+
+/// Replace each `@FILE` argument naming an existing file with the
+/// arguments it holds, one per line
+///
+/// Lines are taken literally (no shell quoting) after trimming; blank lines
+/// and lines starting with `#` are skipped. Response files do not nest.
+pub fn expand_response_files(args: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>> {
+    let mut out = Vec::new();
+    for (i, arg) in args.into_iter().enumerate() {
+        let file = arg.to_str().and_then(|a| a.strip_prefix('@')).map(Path::new);
+        match file {
+            Some(file) if i > 0 && file.is_file() => {
+                let text = fs::read_to_string(file)
+                    .with_context(|| format!("cannot read response file {}", file.display()))?;
+                out.extend(
+                    text.lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .map(OsString::from),
+                );
+            }
+            _ => out.push(arg),
+        }
+    }
+    Ok(out)
+}
+
+/// Parse an octal permission mask such as `644` or `0o004`
+#[cfg(all(unix, feature = "unix-filters"))]
+fn parse_octal(s: &str) -> Result<u32, String> {
+    let digits = s.trim_start_matches("0o");
+    u32::from_str_radix(digits, 8).map_err(|_| format!("invalid octal mask: {}", s))
+}
+
+/// Parse `--sample` as `N` or `N,SEED`
+fn parse_sample(s: &str) -> Result<Sample, String> {
+    let (count, seed) = s.split_once(',').unwrap_or((s, "0"));
+    Ok(Sample {
+        count: count.trim().parse().map_err(|_| format!("invalid sample size: {}", count))?,
+        seed: seed.trim().parse().map_err(|_| format!("invalid seed: {}", seed))?,
+    })
+}
+
+/// Parse a read rate in MB/s into bytes per second
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate * 1024.0 * 1024.0),
+        _ => Err(format!("invalid rate: {} (MB/s, greater than 0)", s)),
+    }
+}
+
+/// Parse a human-readable size such as `500MB`, `1.5G` or `4096`
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size: {}", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        "t" | "tb" | "tib" => 1024 * 1024 * 1024 * 1024,
+        other => return Err(format!("unknown size unit: {}", other)),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size(" 500MB "), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_size("1.5G"), Ok(1536 * 1024 * 1024));
+        assert_eq!(parse_size("2 kib"), Ok(2048));
+        assert_eq!(parse_size("100KB"), Ok(102_400));
+        assert_eq!(parse_size("0.5b"), Ok(0));
+        for bad in ["", "MB", "-1", "1.2.3", "1e3", "10 PB", "ten"] {
+            assert!(parse_size(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[cfg(all(unix, feature = "unix-filters"))]
+    #[test]
+    fn octal_masks() {
+        assert_eq!(parse_octal("644"), Ok(0o644));
+        assert_eq!(parse_octal("0o004"), Ok(0o004));
+        assert!(parse_octal("9").is_err() && parse_octal("").is_err());
+    }
+
+    #[test]
+    fn samples() {
+        assert_eq!(parse_sample("20").map(|s| (s.count, s.seed)), Ok((20, 0)));
+        assert_eq!(parse_sample("20, 7").map(|s| (s.count, s.seed)), Ok((20, 7)));
+        assert!(parse_sample("x,1").is_err() && parse_sample("1,x").is_err());
+    }
+
+    #[test]
+    fn later_options_override_earlier() {
+        // The user config's flags go first, so the command line wins
+        let args = Args::try_parse_from(["treemerge", "--tokenizer", "chars", "--tokenizer", "approx", "."]).unwrap();
+        assert_eq!(args.tokenizer, Tokenizer::Approx);
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(parse_rate("2"), Ok(2.0 * 1024.0 * 1024.0));
+        for bad in ["0", "-1", "inf", "NaN", ""] {
+            assert!(parse_rate(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn response_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("args.txt");
+        fs::write(&file, "# options\n--git-status\n\n  -x  \n*.md\n@nested\n").unwrap();
+        let at = format!("@{}", file.display());
+        let args = ["treemerge", &at, "--", "@not-a-file"].map(OsString::from);
+        assert_eq!(
+            expand_response_files(args).unwrap(),
+            ["treemerge", "--git-status", "-x", "*.md", "@nested", "--", "@not-a-file"].map(OsString::from)
+        );
+        // The program name is never a response file
+        assert_eq!(expand_response_files([OsString::from(&at)]).unwrap(), [OsString::from(&at)]);
+    }
+
+    #[test]
+    fn settings_default_to_no_options() {
+        let parsed = Settings::from(Args::try_parse_from(["treemerge", "."]).unwrap());
+        let default = Settings { path: Some(".".into()), ..Settings::default() };
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", default));
+    }
+}
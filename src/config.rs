@@ -0,0 +1,430 @@
+use crate::cli::{Args, HeaderStyle, Tokenizer};
+use crate::merge;
+use crate::transform::{Pipeline, Rule, Transform};
+use clap::{CommandFactory, Parser, ValueEnum};
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// User defaults from `<config dir>/config.toml`
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    /// Options put in front of the command line's, which override them
+    pub flags: Vec<String>,
+    /// Default `--tokenizer`
+    pub tokenizer: Option<Tokenizer>,
+    /// Patterns added to the built-in excludes
+    pub excludes: Vec<String>,
+    /// Built-in exclude patterns to drop (e.g. `**/LICENSE`)
+    pub remove_excludes: Vec<String>,
+    /// Extension (without the dot) -> language, ahead of the built-in table
+    pub languages: HashMap<String, String>,
+    /// Like `languages`, and files with these extensions are always text
+    #[serde(alias = "ext-aliases")]
+    pub ext_aliases: HashMap<String, String>,
+    /// Content transforms, run in order over the selected files
+    pub transforms: Vec<Transform>,
+    /// Per-glob treatment, run after `transforms`
+    pub rules: Vec<Rule>,
+}
+
+/// Name of the project config looked for in the merged directory
+pub const PROJECT_FILE: &str = "treemerge.toml";
+
+/// Merge defaults checked into a project, from its `treemerge.toml`
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Project {
+    /// `--include` patterns
+    pub include: Vec<String>,
+    /// `--exclude` patterns
+    pub exclude: Vec<String>,
+    /// `--ext` extensions
+    pub ext: Vec<String>,
+    pub header_style: Option<HeaderStyle>,
+    /// `--output`, relative to the file's directory
+    pub output: Option<PathBuf>,
+    pub split_every: Option<usize>,
+    /// Any other options
+    pub flags: Vec<String>,
+    /// `[profile.NAME]` tables, applied over the keys above with `--profile`
+    pub profile: HashMap<String, Project>,
+}
+
+impl Project {
+    /// The keys as options, paths resolved against `dir`
+    fn options(&self, dir: &Path) -> Vec<OsString> {
+        let mut out = Vec::new();
+        let mut push = |flag: &str, value: OsString| {
+            out.push(flag.into());
+            out.push(value);
+        };
+        for pattern in &self.include {
+            push("--include", pattern.into());
+        }
+        for pattern in &self.exclude {
+            push("--exclude", pattern.into());
+        }
+        for ext in &self.ext {
+            push("--ext", ext.into());
+        }
+        if let Some(value) = self.header_style.and_then(|s| s.to_possible_value()) {
+            push("--header-style", value.get_name().into());
+        }
+        if let Some(output) = &self.output {
+            push("--output", dir.join(output).into());
+        }
+        if let Some(lines) = self.split_every {
+            push("--split-every", lines.to_string().into());
+        }
+        out.extend(self.flags.iter().map(OsString::from));
+        out
+    }
+}
+
+/// Options a `treemerge.toml` found in the merged directory may pass in
+/// `flags`: what is selected and how it is laid out. Options that run
+/// commands, reach the network, or read or write files elsewhere need the
+/// file named with `--config`, or `--trust-project`.
+const PROJECT_SAFE: &[&str] = &[
+    "include", "exclude", "ext", "only", "all_files", "no_default_exclude", "default_exclude_only",
+    "use_gitignore", "no_gitignore", "lfs", "submodules", "split_every", "max_tokens", "header_style",
+    "format", "delimiters", "output_encoding", "git_preamble", "git_meta", "blame_summary",
+    "git_status", "diff", "diff_full_under", "changed_with_context", "deps_summary", "sort",
+    "list_sort", "entry_points", "dep_graph", "per_file_stats", "toc", "dir_summaries", "stable_ids",
+    "chunk_tokens", "root_label", "output_per_root", "color", "progress_format", "dry_run",
+    "count_tokens", "explain", "max_output_size", "redact_pii", "redact", "scan_secrets",
+    "native_separators", "owned_by", "perm", "metadata", "timestamp", "include_merged",
+    "basename_hints", "symlink_stubs", "package", "tokenizer", "digest", "summarize_threshold",
+    "budget_tokens", "trim_strategy", "max_file_tokens", "overflow", "max_files", "max_files_by",
+    "sample", "sample_by", "stats", "top", "depth", "io_limit", "nice_io", "lock_retries",
+    "lock_retry_delay", "io_retries", "io_retry_delay", "debug_globs", "verbose",
+];
+
+/// Refuse `flags` of the untrusted project config at `path` that are not in
+/// `PROJECT_SAFE`, and an `output` outside its directory
+fn check_untrusted(path: &Path, project: &Project) -> Result<()> {
+    let refuse = |what: &str| {
+        anyhow!(
+            "{}: {} cannot be set by a {} found in the merged directory; name the file with --config or pass --trust-project",
+            path.display(),
+            what,
+            PROJECT_FILE
+        )
+    };
+    if let Some(output) = &project.output
+        && (output.is_absolute() || output.components().any(|c| c == std::path::Component::ParentDir))
+    {
+        return Err(refuse("an output outside its directory"));
+    }
+    // Parsed apart from the command line: only what the file sets is seen
+    let mut argv: Vec<OsString> = vec!["treemerge".into(), ".".into()];
+    argv.extend(project.flags.iter().map(OsString::from));
+    let command = Args::command().ignore_errors(true);
+    let matches = command
+        .clone()
+        .try_get_matches_from(argv)
+        .with_context(|| format!("invalid config {}", path.display()))?;
+    let set = |arg: &&clap::Arg| {
+        matches.value_source(arg.get_id().as_str()) == Some(clap::parser::ValueSource::CommandLine)
+    };
+    match command
+        .get_arguments()
+        .filter(set)
+        .find(|arg| arg.get_id() != "path" && !PROJECT_SAFE.contains(&arg.get_id().as_str()))
+    {
+        Some(arg) => Err(refuse(&format!("--{}", arg.get_long().unwrap_or(arg.get_id().as_str())))),
+        None => Ok(()),
+    }
+}
+
+/// Options from the project config of the merge `args` describe: the file
+/// given with `--config`, or `treemerge.toml` in the root directory, then
+/// the `--profile` table
+///
+/// Empty when there is no such file, or with `--no-config`. A file found in
+/// the root is only trusted with `--trust-project`; see `PROJECT_SAFE`.
+fn project_options(args: &Args) -> Result<Vec<OsString>> {
+    let path = match (&args.config, &args.path) {
+        (Some(path), _) => path.clone(),
+        (None, Some(dir)) if !args.no_config => dir.join(PROJECT_FILE),
+        _ => PathBuf::new(),
+    };
+    if args.config.is_none() && !path.is_file() {
+        return match &args.profile {
+            Some(name) => Err(anyhow!("--profile {}: no {} found (use --config)", name, PROJECT_FILE)),
+            None => Ok(Vec::new()),
+        };
+    }
+    let text = fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))?;
+    let project: Project =
+        toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))?;
+    if project.profile.values().any(|p| !p.profile.is_empty()) {
+        return Err(anyhow!("invalid config {}: profiles do not nest", path.display()));
+    }
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut options = project.options(dir);
+    if args.config.is_none() && !args.trust_project {
+        check_untrusted(&path, &project)?;
+    }
+    if let Some(name) = &args.profile {
+        let Some(profile) = project.profile.get(name) else {
+            let mut names: Vec<&str> = project.profile.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            return Err(anyhow!(
+                "{} has no profile {:?} (profiles: {})",
+                path.display(),
+                name,
+                if names.is_empty() { "none".to_string() } else { names.join(", ") }
+            ));
+        };
+        if args.config.is_none() && !args.trust_project {
+            check_untrusted(&path, profile)?;
+        }
+        options.extend(profile.options(dir));
+    }
+    Ok(options)
+}
+
+/// JSON Schema of `config.toml`, kept in step with `Config`
+pub const SCHEMA: &str = include_str!("config.schema.json");
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Per-user configuration directory (`$XDG_CONFIG_HOME/treemerge` or equivalent)
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    base.map(|b| b.join("treemerge"))
+}
+
+/// Location of the user config file
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("config.toml"))
+}
+
+/// Read the user config; a missing file is an empty config
+///
+/// Unknown keys and values of the wrong type are errors, reported with
+/// their line and column.
+pub fn load() -> Result<Config> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(e).with_context(|| format!("cannot read {}", path.display())),
+    };
+    let mut config: Config =
+        toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))?;
+    let normalize = |table: HashMap<String, String>| {
+        table
+            .into_iter()
+            .map(|(ext, lang)| (ext.trim_start_matches('.').to_ascii_lowercase(), lang))
+            .collect()
+    };
+    config.languages = normalize(config.languages);
+    config.ext_aliases = normalize(config.ext_aliases);
+    // Checked here so a bad pipeline fails before any scanning
+    Pipeline::new(&config.pipeline()).with_context(|| format!("invalid config {}", path.display()))?;
+    for pattern in &config.remove_excludes {
+        if !merge::is_builtin_exclude(pattern) {
+            eprintln!(
+                "warning: {}: {} is not a built-in exclude",
+                path.display(),
+                pattern
+            );
+        }
+    }
+    Ok(config)
+}
+
+impl Config {
+    /// `transforms`, then the steps of every rule in order
+    pub fn pipeline(&self) -> Vec<Transform> {
+        let rules = self.rules.iter().flat_map(Rule::transforms);
+        self.transforms.iter().cloned().chain(rules).collect()
+    }
+}
+
+/// Whether `path` has an extension aliased in the config
+pub fn is_aliased(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| get().ext_aliases.contains_key(&ext.to_ascii_lowercase()))
+}
+
+/// Make `config` the one `get` returns for the rest of the run
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The config in effect (empty unless `init` was called)
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// A merge command line `raw` (program name first) with the user's
+/// defaults, then the project's, inserted after the program name
+pub fn merge_argv(raw: impl IntoIterator<Item = OsString>) -> Result<Vec<OsString>> {
+    let raw: Vec<OsString> = raw.into_iter().collect();
+    // The root (and --config, --profile) are only known once parsed; clap
+    // reports a bad command line when it is parsed for good
+    let Ok(args) = Args::try_parse_from(argv(raw.clone(), &[])) else {
+        return Ok(argv(raw, &[]));
+    };
+    let project = project_options(&args)?;
+    Ok(argv(raw, &project))
+}
+
+/// Command line `args` (program name first) with the configured defaults,
+/// then `project` options, inserted after the program name
+fn argv(args: impl IntoIterator<Item = OsString>, project: &[OsString]) -> Vec<OsString> {
+    let config = get();
+    let mut args = args.into_iter();
+    let mut out: Vec<OsString> = args.next().into_iter().collect();
+    if let Some(value) = config.tokenizer.and_then(|t| t.to_possible_value()) {
+        out.push("--tokenizer".into());
+        out.push(value.get_name().into());
+    }
+    out.extend(config.flags.iter().map(OsString::from));
+    out.extend_from_slice(project);
+    out.extend(args);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Step;
+
+    #[test]
+    fn user_defaults() {
+        let config: Config = toml::from_str("flags = [\"--git-status\"]\ntokenizer = \"chars\"\n[languages]\nsv = \"verilog\"\n").unwrap();
+        assert_eq!(config.flags, ["--git-status"]);
+        assert_eq!(config.tokenizer, Some(Tokenizer::Chars));
+        assert_eq!(config.languages["sv"], "verilog");
+        assert!(toml::from_str::<Config>("flag = []").is_err());
+        assert!(toml::from_str::<Config>("tokenizer = \"fast\"").is_err());
+    }
+
+    #[test]
+    fn removed_excludes() {
+        let config: Config = toml::from_str("remove_excludes = [\"**/LICENSE\"]\n").unwrap();
+        assert_eq!(config.remove_excludes, ["**/LICENSE"]);
+    }
+
+    #[test]
+    fn schema() {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        assert_eq!(schema["additionalProperties"], false);
+        let names: Vec<String> = Tokenizer::value_variants()
+            .iter()
+            .filter_map(|t| t.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        assert_eq!(schema["properties"]["tokenizer"]["enum"], serde_json::json!(names));
+    }
+
+    #[test]
+    fn ext_aliases() {
+        let config: Config = toml::from_str("[ext-aliases]\ntpl = \"html\"\n").unwrap();
+        assert_eq!(config.ext_aliases["tpl"], "html");
+        let config: Config = toml::from_str("[ext_aliases]\nmk = \"make\"\n").unwrap();
+        assert_eq!(config.ext_aliases["mk"], "make");
+        assert!(config.languages.is_empty());
+    }
+
+    #[test]
+    fn rules_run_after_transforms() {
+        let config: Config = toml::from_str(
+            "[[rules]]\nmatch = \"docs/**\"\nsqueeze_blank = true\n\n[[transforms]]\nstep = \"strip-comments\"\n",
+        )
+        .unwrap();
+        let steps: Vec<_> = config.pipeline().iter().map(|t| (t.step, t.paths.clone())).collect();
+        assert_eq!(steps, [(Step::StripComments, vec![]), (Step::SqueezeBlank, vec!["docs/**".to_string()])]);
+    }
+
+    #[test]
+    fn project_files_and_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = "exclude = [\"*.md\"]\nheader_style = \"underline\"\noutput = \"out/all.txt\"\n\n[profile.api]\ninclude = [\"api/**\"]\nflags = [\"--git-status\"]\n";
+        fs::write(dir.path().join(PROJECT_FILE), text).unwrap();
+        let parse = |extra: &[&str]| {
+            let mut argv = vec!["treemerge".to_string()];
+            argv.extend(extra.iter().map(|a| a.to_string()));
+            argv.extend(["--".into(), dir.path().display().to_string()]);
+            project_options(&Args::parse_from(argv))
+        };
+        let out = dir.path().join("out/all.txt");
+        let expected = ["--exclude", "*.md", "--header-style", "underline", "--output", out.to_str().unwrap()];
+        assert_eq!(parse(&[]).unwrap(), expected.map(OsString::from));
+        let with_profile = parse(&["--profile", "api"]).unwrap();
+        assert_eq!(with_profile[expected.len()..], ["--include", "api/**", "--git-status"].map(OsString::from));
+        assert!(parse(&["--no-config"]).unwrap().is_empty());
+        let err = parse(&["--profile", "web"]).unwrap_err();
+        assert!(err.to_string().contains("has no profile \"web\" (profiles: api)"), "{}", err);
+        assert!(toml::from_str::<Project>("[profile.a.profile.b]\n").is_ok());
+        assert!(toml::from_str::<Project>("outptu = \"x\"\n").is_err());
+    }
+
+    fn project(flags: &[&str]) -> Project {
+        Project {
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            ..Project::default()
+        }
+    }
+
+    #[test]
+    fn untrusted_projects_select_and_lay_out() {
+        let path = Path::new("treemerge.toml");
+        assert!(check_untrusted(path, &project(&["--only", "code", "--max-tokens=1000", "-x", "*.md"])).is_ok());
+        for flags in [
+            &["--capture", "touch /tmp/x"][..],
+            &["--capture=touch /tmp/x"],
+            &["--summarize-large", "sh"],
+            &["--upload-url", "http://example.com"],
+            &["--open"],
+            &["--only", "code", "--pager"],
+            &["--order-file", "/etc/passwd"],
+            &["--diff-previous"],
+            &["--no-redact"],
+        ] {
+            assert!(check_untrusted(path, &project(flags)).is_err(), "{:?}", flags);
+        }
+    }
+
+    #[test]
+    fn project_safe_names_options() {
+        let command = Args::command();
+        for id in PROJECT_SAFE {
+            assert!(command.get_arguments().any(|arg| arg.get_id() == *id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn untrusted_outputs_stay_in_the_directory() {
+        let path = Path::new("treemerge.toml");
+        let mut p = project(&[]);
+        p.output = Some("out/merged.txt".into());
+        assert!(check_untrusted(path, &p).is_ok());
+        for output in ["../merged.txt", "/tmp/merged.txt"] {
+            p.output = Some(output.into());
+            assert!(check_untrusted(path, &p).is_err(), "{}", output);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::cli::DepGraphFormat;
+use crate::imports::ImportIndex;
+use crate::lang;
+use rayon::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files each file imports, restricted to `files`, in input order
+pub fn edges(files: &[PathBuf]) -> Vec<(PathBuf, Vec<PathBuf>)> {
+    let index = ImportIndex::new(files);
+    files
+        .par_iter()
+        .filter_map(|file| {
+            let lang = lang::language_for(file)?;
+            let content = fs::read_to_string(file).ok()?;
+            let targets = index.resolve(lang, file, &content);
+            (!targets.is_empty()).then(|| (file.clone(), targets))
+        })
+        .collect()
+}
+
+/// Render the graph as an indented list or a DOT digraph
+pub fn render(
+    edges: &[(PathBuf, Vec<PathBuf>)],
+    format: DepGraphFormat,
+    shown: impl Fn(&Path) -> String,
+) -> String {
+    let mut out = String::new();
+    match format {
+        DepGraphFormat::List => {
+            for (file, targets) in edges {
+                out.push_str(&format!("{}\n", shown(file)));
+                for target in targets {
+                    out.push_str(&format!("  -> {}\n", shown(target)));
+                }
+            }
+        }
+        DepGraphFormat::Dot => {
+            out.push_str("digraph deps {\n");
+            for (file, targets) in edges {
+                for target in targets {
+                    out.push_str(&format!("  {:?} -> {:?};\n", shown(file), shown(target)));
+                }
+            }
+            out.push_str("}\n");
+        }
+    }
+    let count: usize = edges.iter().map(|(_, t)| t.len()).sum();
+    if count == 0 && format == DepGraphFormat::List {
+        out.push_str("(no imports between the included files)\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_between_the_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("src/main.rs");
+        let cli = dir.path().join("src/cli.rs");
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(&main, "mod cli;\nuse std::fs;\n").unwrap();
+        fs::write(&cli, "pub struct Args;\n").unwrap();
+        let files = vec![main.clone(), cli.clone()];
+        let edges = edges(&files);
+        assert_eq!(edges, [(main, vec![cli])]);
+        let shown = |p: &Path| p.strip_prefix(dir.path()).unwrap().display().to_string();
+        assert_eq!(render(&edges, DepGraphFormat::List, shown), "src/main.rs\n  -> src/cli.rs\n");
+        assert_eq!(
+            render(&edges, DepGraphFormat::Dot, shown),
+            "digraph deps {\n  \"src/main.rs\" -> \"src/cli.rs\";\n}\n"
+        );
+        assert_eq!(render(&[], DepGraphFormat::List, shown), "(no imports between the included files)\n");
+    }
+}
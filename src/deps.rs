@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// One direct dependency declared in a manifest
+pub struct Dependency {
+    pub name: String,
+    /// Version requirement, or where it comes from (`path`, `git`, `workspace`)
+    pub version: String,
+    /// `normal`, `dev`, `build`, `peer`, `optional` or an extras group
+    pub kind: String,
+}
+
+type Parser = fn(&str) -> Result<Vec<Dependency>>;
+
+/// Version requirement of a Cargo dependency, plain or in table form
+fn cargo_version(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(v) => v.clone(),
+        toml::Value::Table(t) => {
+            if let Some(v) = t.get("version").and_then(|v| v.as_str()) {
+                v.to_owned()
+            } else if t.get("workspace").and_then(|v| v.as_bool()) == Some(true) {
+                "workspace".into()
+            } else if let Some(p) = t.get("path").and_then(|v| v.as_str()) {
+                format!("path {}", p)
+            } else if let Some(g) = t.get("git").and_then(|v| v.as_str()) {
+                format!("git {}", g)
+            } else {
+                "*".into()
+            }
+        }
+        _ => "*".into(),
+    }
+}
+
+fn cargo(text: &str) -> Result<Vec<Dependency>> {
+    let doc: toml::Table = toml::from_str(text)?;
+    let mut out = Vec::new();
+    let mut collect = |table: &toml::Table, prefix: &str| {
+        for (section, kind) in [
+            ("dependencies", "normal"),
+            ("dev-dependencies", "dev"),
+            ("build-dependencies", "build"),
+        ] {
+            let Some(deps) = table.get(section).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for (name, value) in deps {
+                out.push(Dependency {
+                    name: name.clone(),
+                    version: cargo_version(value),
+                    kind: format!("{}{}", prefix, kind),
+                });
+            }
+        }
+    };
+    collect(&doc, "");
+    if let Some(targets) = doc.get("target").and_then(|t| t.as_table()) {
+        for (cfg, table) in targets {
+            if let Some(table) = table.as_table() {
+                collect(table, &format!("{} ", cfg));
+            }
+        }
+    }
+    if let Some(workspace) = doc.get("workspace").and_then(|w| w.as_table())
+        && let Some(deps) = workspace.get("dependencies").and_then(|d| d.as_table())
+    {
+        for (name, value) in deps {
+            out.push(Dependency {
+                name: name.clone(),
+                version: cargo_version(value),
+                kind: "workspace".into(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+fn npm(text: &str) -> Result<Vec<Dependency>> {
+    let doc: serde_json::Value = serde_json::from_str(text)?;
+    let mut out = Vec::new();
+    for (section, kind) in [
+        ("dependencies", "normal"),
+        ("devDependencies", "dev"),
+        ("peerDependencies", "peer"),
+        ("optionalDependencies", "optional"),
+    ] {
+        let Some(deps) = doc.get(section).and_then(|d| d.as_object()) else {
+            continue;
+        };
+        for (name, version) in deps {
+            out.push(Dependency {
+                name: name.clone(),
+                version: version.as_str().unwrap_or("*").to_owned(),
+                kind: kind.into(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Split a PEP 508 requirement such as `requests[socks]>=2.31; python_version<"4"`
+fn pep508(req: &str, kind: &str) -> Dependency {
+    let req = req.split(';').next().unwrap_or(req).trim();
+    let end = req
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(req.len());
+    let (name, rest) = req.split_at(end);
+    let rest = rest.trim();
+    // Drop extras, keep the version specifier
+    let rest = match rest.strip_prefix('[') {
+        Some(r) => r.split_once(']').map_or("", |(_, v)| v).trim(),
+        None => rest,
+    };
+    Dependency {
+        name: name.to_owned(),
+        version: if rest.is_empty() { "*".into() } else { rest.to_owned() },
+        kind: kind.to_owned(),
+    }
+}
+
+fn pyproject(text: &str) -> Result<Vec<Dependency>> {
+    let doc: toml::Table = toml::from_str(text)?;
+    let mut out = Vec::new();
+    let strings = |v: &toml::Value| -> Vec<String> {
+        v.as_array()
+            .map(|a| a.iter().filter_map(|s| s.as_str().map(str::to_owned)).collect())
+            .unwrap_or_default()
+    };
+
+    if let Some(project) = doc.get("project").and_then(|p| p.as_table()) {
+        if let Some(deps) = project.get("dependencies") {
+            out.extend(strings(deps).iter().map(|r| pep508(r, "normal")));
+        }
+        if let Some(groups) = project.get("optional-dependencies").and_then(|g| g.as_table()) {
+            for (group, deps) in groups {
+                out.extend(strings(deps).iter().map(|r| pep508(r, &format!("extra {}", group))));
+            }
+        }
+    }
+    if let Some(groups) = doc.get("dependency-groups").and_then(|g| g.as_table()) {
+        for (group, deps) in groups {
+            out.extend(strings(deps).iter().map(|r| pep508(r, &format!("group {}", group))));
+        }
+    }
+
+    let poetry = doc
+        .get("tool")
+        .and_then(|t| t.get("poetry"))
+        .and_then(|p| p.as_table());
+    if let Some(poetry) = poetry {
+        for (section, kind) in [("dependencies", "normal"), ("dev-dependencies", "dev")] {
+            let Some(deps) = poetry.get(section).and_then(|d| d.as_table()) else {
+                continue;
+            };
+            for (name, value) in deps.iter().filter(|(n, _)| n.as_str() != "python") {
+                out.push(Dependency {
+                    name: name.clone(),
+                    version: cargo_version(value),
+                    kind: kind.into(),
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Ecosystem and direct dependencies of a manifest, if `path` is one
+pub fn parse(path: &Path) -> Result<Option<(&'static str, Vec<Dependency>)>> {
+    let (ecosystem, parser): (_, Parser) =
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some("Cargo.toml") => ("cargo", cargo),
+            Some("package.json") => ("npm", npm),
+            Some("pyproject.toml") => ("python", pyproject),
+            _ => return Ok(None),
+        };
+    let text = fs::read_to_string(path)?;
+    let deps = parser(&text).with_context(|| format!("cannot parse {}", path.display()))?;
+    Ok(Some((ecosystem, deps)))
+}
+
+/// Compact table per manifest: name, version requirement and kind
+pub fn render(manifests: &[(String, &'static str, Vec<Dependency>)]) -> String {
+    let mut out = String::new();
+    for (path, ecosystem, deps) in manifests {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{} ({}, {} direct)\n", path, ecosystem, deps.len()));
+        let name_w = deps.iter().map(|d| d.name.len()).max().unwrap_or(0).max(4);
+        let ver_w = deps.iter().map(|d| d.version.len()).max().unwrap_or(0).max(7);
+        out.push_str(&format!("  {:<name_w$}  {:<ver_w$}  kind\n", "name", "version"));
+        for d in deps {
+            out.push_str(&format!(
+                "  {:<name_w$}  {:<ver_w$}  {}\n",
+                d.name, d.version, d.kind
+            ));
+        }
+    }
+    if out.is_empty() {
+        out.push_str("(no Cargo.toml, package.json or pyproject.toml among the included files)\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(deps: &[Dependency]) -> Vec<(&str, &str, &str)> {
+        deps.iter().map(|d| (d.name.as_str(), d.version.as_str(), d.kind.as_str())).collect()
+    }
+
+    #[test]
+    fn manifests() {
+        let cargo_toml = "[dependencies]\nanyhow = \"1\"\nlocal = { path = \"../local\" }\n\n[dev-dependencies]\ntempfile = { version = \"3\" }\n\n[target.'cfg(unix)'.dependencies]\nlibc = { workspace = true }\n";
+        assert_eq!(
+            listed(&cargo(cargo_toml).unwrap()),
+            [("anyhow", "1", "normal"), ("local", "path ../local", "normal"), ("tempfile", "3", "dev"), ("libc", "workspace", "cfg(unix) normal")]
+        );
+        let package_json = r#"{"dependencies": {"react": "^18"}, "peerDependencies": {"vue": "*"}}"#;
+        assert_eq!(listed(&npm(package_json).unwrap()), [("react", "^18", "normal"), ("vue", "*", "peer")]);
+        let pyproject_toml = "[project]\ndependencies = ['requests[socks]>=2.31; python_version<\"4\"', 'rich']\n\n[tool.poetry.dependencies]\npython = \"^3.11\"\nclick = \"^8\"\n";
+        assert_eq!(
+            listed(&pyproject(pyproject_toml).unwrap()),
+            [("requests", ">=2.31", "normal"), ("rich", "*", "normal"), ("click", "^8", "normal")]
+        );
+        assert!(cargo("not toml [").is_err());
+    }
+
+    #[test]
+    fn tables() {
+        let deps = vec![Dependency { name: "anyhow".into(), version: "1".into(), kind: "normal".into() }];
+        assert_eq!(
+            render(&[("Cargo.toml".into(), "cargo", deps)]),
+            "Cargo.toml (cargo, 1 direct)\n  name    version  kind\n  anyhow  1        normal\n"
+        );
+        assert!(render(&[]).starts_with("(no Cargo.toml"));
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "x").unwrap();
+        assert!(parse(&dir.path().join("README.md")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::cli::OutputEncoding;
+use std::io::{self, Write};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+
+fn bom(encoding: OutputEncoding) -> &'static [u8] {
+    match encoding {
+        OutputEncoding::Utf8 => b"",
+        OutputEncoding::Utf8Bom => UTF8_BOM,
+        OutputEncoding::Utf16le => UTF16LE_BOM,
+    }
+}
+
+fn push_utf16le(out: &mut Vec<u8>, text: &str) {
+    for unit in text.encode_utf16() {
+        out.extend_from_slice(&unit.to_le_bytes());
+    }
+}
+
+/// `text` in `encoding`, byte order mark included, for outputs written whole
+pub fn encode(encoding: OutputEncoding, text: &str) -> Vec<u8> {
+    let mut out = bom(encoding).to_vec();
+    match encoding {
+        OutputEncoding::Utf8 | OutputEncoding::Utf8Bom => out.extend_from_slice(text.as_bytes()),
+        OutputEncoding::Utf16le => push_utf16le(&mut out, text),
+    }
+    out
+}
+
+/// Writer taking UTF-8 and passing it on in `encoding`, starting with its
+/// byte order mark
+pub struct Encoder<W> {
+    inner: W,
+    encoding: OutputEncoding,
+    /// Start of a character split across writes
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(mut inner: W, encoding: OutputEncoding) -> io::Result<Encoder<W>> {
+        inner.write_all(bom(encoding))?;
+        Ok(Encoder {
+            inner,
+            encoding,
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.encoding != OutputEncoding::Utf16le {
+            return self.inner.write(buf);
+        }
+        self.pending.extend_from_slice(buf);
+        let mut out = Vec::with_capacity(self.pending.len() * 2);
+        let mut rest = &self.pending[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    push_utf16le(&mut out, text);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // Checked by from_utf8 just now
+                    push_utf16le(&mut out, std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        // Incomplete: wait for the rest of the character
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                        Some(len) => {
+                            push_utf16le(&mut out, "\u{FFFD}");
+                            rest = &after[len..];
+                        }
+                    }
+                }
+            }
+        }
+        self.pending = rest.to_vec();
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Text of an output written in any of the encodings, told apart by its
+/// byte order mark
+pub fn decode(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        let units: Vec<u16> = rest
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    String::from_utf8_lossy(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes)).into_owned()
+}
+
+/// Whether `bytes` start with `prefix` in any of the output encodings
+pub fn starts_with(bytes: &[u8], prefix: &str) -> bool {
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        let mut wide = Vec::new();
+        push_utf16le(&mut wide, prefix);
+        return rest.starts_with(&wide);
+    }
+    bytes
+        .strip_prefix(UTF8_BOM)
+        .unwrap_or(bytes)
+        .starts_with(prefix.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_order_marks() {
+        assert_eq!(encode(OutputEncoding::Utf8, "ü"), "ü".as_bytes());
+        assert_eq!(encode(OutputEncoding::Utf8Bom, "a"), b"\xEF\xBB\xBFa");
+        assert_eq!(encode(OutputEncoding::Utf16le, "a🦀"), b"\xFF\xFEa\0\x3E\xD8\x80\xDD");
+        for encoding in [OutputEncoding::Utf8, OutputEncoding::Utf8Bom, OutputEncoding::Utf16le] {
+            let bytes = encode(encoding, "@treemerge-output 0.0.5\n");
+            assert!(starts_with(&bytes, "@treemerge-output"), "{:?}", encoding);
+            assert!(!starts_with(&bytes, "@treemerge-outpux"), "{:?}", encoding);
+        }
+        assert!(!starts_with(&[0xFF, 0xFE, b'a'], "a"));
+    }
+
+    #[test]
+    fn round_trips_by_byte_order_mark() {
+        for encoding in [OutputEncoding::Utf8, OutputEncoding::Utf8Bom, OutputEncoding::Utf16le] {
+            let bytes = encode(encoding, "@treemerge-output ü 🦀\r\n");
+            assert_eq!(decode(&bytes), "@treemerge-output ü 🦀\r\n", "{:?}", encoding);
+            assert!(starts_with(&bytes, "@treemerge-output"), "{:?}", encoding);
+            assert!(!starts_with(&bytes, "@treemerge-outpux"), "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn odd_and_broken_bytes() {
+        // A trailing half of a UTF-16 unit and invalid UTF-8 are dropped or replaced, not fatal
+        assert_eq!(decode(&[0xFF, 0xFE, b'a', 0, b'b']), "a");
+        assert_eq!(decode(&[b'a', 0xC3]), "a\u{FFFD}");
+        assert_eq!(decode(&[0xEF, 0xBB, 0xBF]), "");
+    }
+}
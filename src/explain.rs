@@ -0,0 +1,514 @@
+use crate::cli::{Args, FileKind, SubmoduleMode};
+use crate::config;
+use crate::style::Style;
+use crate::merge::{
+    IGNORE_FILE, default_excludes, exclude_category, found_verdict, include_patterns, rel_path, section_verdict,
+};
+use crate::git;
+use crate::lang;
+use crate::settings::Settings;
+use crate::skip::SkipList;
+use crate::workspace;
+use anyhow::{Context, Result, anyhow};
+use clap::{Parser, ValueEnum};
+use globset::{Glob, GlobMatcher};
+use ignore::Match;
+use ignore::gitignore::{self, Gitignore, GitignoreBuilder};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Glob patterns kept one by one, so a hit can be named
+struct Patterns(Vec<(String, GlobMatcher)>);
+
+impl Patterns {
+    fn new(patterns: &[String]) -> Result<Patterns> {
+        let mut out = Vec::new();
+        for p in patterns {
+            let glob = Glob::new(p).context("Invalid glob pattern")?;
+            out.push((p.clone(), glob.compile_matcher()));
+        }
+        Ok(Patterns(out))
+    }
+
+    fn hits<'a>(&'a self, rel: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |(_, m)| m.is_match(rel))
+            .map(|(p, _)| p.as_str())
+    }
+}
+
+/// The gitignore files of the repository containing a tree
+///
+/// Mirrors what the scan honors: nested `.gitignore` files (deeper ones
+/// win), then `.git/info/exclude`, then the global excludes file. Also
+/// reads `.treemergeignore` files, which have no repository-wide ones.
+struct GitIgnores {
+    /// Topmost directory whose files are read
+    repo_root: PathBuf,
+    /// What rule files are named relative to
+    base: PathBuf,
+    /// Name of the per-directory files
+    file: &'static str,
+    /// Per-directory file of each directory, read on first use
+    dirs: RefCell<HashMap<PathBuf, Option<Gitignore>>>,
+    exclude: Gitignore,
+    global: Gitignore,
+}
+
+impl GitIgnores {
+    /// The `file` files (`.treemergeignore`, `.ignore`) in and above `root`,
+    /// read inside and outside git repositories alike
+    fn anywhere(root: &Path, file: &'static str) -> Option<GitIgnores> {
+        let canonical = root.canonicalize().ok()?;
+        let top = canonical.ancestors().last()?.to_path_buf();
+        Some(GitIgnores {
+            repo_root: top,
+            base: canonical,
+            file,
+            dirs: RefCell::new(HashMap::new()),
+            exclude: Gitignore::empty(),
+            global: Gitignore::empty(),
+        })
+    }
+
+    /// None when `root` is not inside a git repository, where the scan
+    /// ignores `.gitignore` files too
+    fn new(root: &Path) -> Option<GitIgnores> {
+        let canonical = root.canonicalize().ok()?;
+        let repo_root = canonical.ancestors().find(|d| d.join(".git").exists())?.to_path_buf();
+        let mut exclude = GitignoreBuilder::new(&repo_root);
+        exclude.add(repo_root.join(".git").join("info").join("exclude"));
+        let exclude = exclude.build().unwrap_or_else(|_| Gitignore::empty());
+        let (global, _) = GitignoreBuilder::new(&repo_root).build_global();
+        Some(GitIgnores {
+            base: repo_root.clone(),
+            repo_root,
+            file: ".gitignore",
+            dirs: RefCell::new(HashMap::new()),
+            exclude,
+            global,
+        })
+    }
+
+    /// The rule deciding `path` (canonical), and whether it ignores it
+    fn rule(&self, path: &Path) -> Option<(bool, String)> {
+        let mut dirs = self.dirs.borrow_mut();
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.repo_root) {
+                break;
+            }
+            let matcher = dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+                let file = dir.join(self.file);
+                if !file.is_file() {
+                    return None;
+                }
+                let mut builder = GitignoreBuilder::new(dir);
+                builder.add(file);
+                builder.build().ok()
+            });
+            if let Some(m) = matcher
+                && let Some(found) = self.describe(m.matched_path_or_any_parents(path, false))
+            {
+                return Some(found);
+            }
+        }
+        [&self.exclude, &self.global]
+            .into_iter()
+            .find_map(|m| self.describe(m.matched_path_or_any_parents(path, false)))
+    }
+
+    fn describe(&self, m: Match<&gitignore::Glob>) -> Option<(bool, String)> {
+        let (ignored, glob) = match m {
+            Match::None => return None,
+            Match::Ignore(g) => (true, g),
+            Match::Whitelist(g) => (false, g),
+        };
+        let from = glob.from().map_or_else(
+            || "gitignore".to_string(),
+            |f| f.strip_prefix(&self.base).unwrap_or(f).display().to_string(),
+        );
+        let verb = if ignored { "ignored" } else { "re-included" };
+        Some((ignored, format!("{} by {} rule {}", verb, from, glob.original())))
+    }
+}
+
+/// Outcome of running one path through the filters
+pub struct Decision {
+    pub included: bool,
+    /// The rule that decided: the first exclusion, or what let the file in
+    pub reason: String,
+    /// Every rule the path hit, in pipeline order
+    pub hits: Vec<String>,
+}
+
+impl Decision {
+    fn hit(&mut self, rule: String, excludes: bool) {
+        if excludes && self.included {
+            self.included = false;
+            self.reason = rule.clone();
+        }
+        self.hits.push(rule);
+    }
+}
+
+/// The scan's filters, able to say which one applies to a path
+pub struct Rules<'a> {
+    root: &'a Path,
+    args: &'a Settings,
+    includes: Patterns,
+    excludes: Patterns,
+    builtin: Patterns,
+    /// Root-relative directories of the `--package` slice
+    package_dirs: Option<Vec<String>>,
+    /// Root-relative submodule directories left out by `--submodules skip`
+    submodules: Vec<String>,
+    /// Paths from `--skip-from`
+    skip_list: Option<SkipList>,
+    gitignores: Option<GitIgnores>,
+    /// `.ignore` files, with the gitignores
+    dot_ignores: Option<GitIgnores>,
+    /// `.treemergeignore` files, unless `--all-files`
+    treemergeignores: Option<GitIgnores>,
+    #[cfg(all(unix, feature = "unix-filters"))]
+    ownership: crate::unix::OwnershipFilter,
+}
+
+impl<'a> Rules<'a> {
+    pub fn new(root: &'a Path, args: &'a Settings) -> Result<Rules<'a>> {
+        let package_dirs = match &args.package {
+            Some(name) => Some(
+                workspace::slice(root, name)?
+                    .iter()
+                    .map(|d| rel_path(root, d))
+                    .collect(),
+            ),
+            None => None,
+        };
+        let use_gitignore = !args.no_gitignore && !args.all_files;
+        Ok(Rules {
+            root,
+            args,
+            includes: Patterns::new(&include_patterns(args)?)?,
+            excludes: Patterns::new(&args.exclude)?,
+            builtin: Patterns::new(&default_excludes(args))?,
+            package_dirs,
+            submodules: match args.submodules {
+                SubmoduleMode::Include => Vec::new(),
+                SubmoduleMode::Skip => git::submodules(root).into_iter().map(|(dir, _)| dir).collect(),
+            },
+            skip_list: args.skip_from.as_deref().map(SkipList::load).transpose()?,
+            gitignores: if use_gitignore { GitIgnores::new(root) } else { None },
+            dot_ignores: if use_gitignore { GitIgnores::anywhere(root, ".ignore") } else { None },
+            treemergeignores: if args.all_files { None } else { GitIgnores::anywhere(root, IGNORE_FILE) },
+            #[cfg(all(unix, feature = "unix-filters"))]
+            ownership: crate::unix::OwnershipFilter::new(args.owned_by.as_deref(), args.perm)?,
+        })
+    }
+
+    /// Run `path` through the filters in the order the scan applies them
+    ///
+    /// Stops at the first exclusion unless `thorough`, in which case every
+    /// rule is checked and recorded.
+    pub fn decide(&self, path: &Path, thorough: bool) -> Decision {
+        let rel = rel_path(self.root, path);
+        let mut d = Decision {
+            included: true,
+            reason: "passed all filters".into(),
+            hits: Vec::new(),
+        };
+
+        if let Some(dirs) = &self.package_dirs {
+            let inside = dirs.iter().any(|dir| {
+                dir.is_empty() || rel.strip_prefix(dir.as_str()).is_some_and(|r| r.starts_with('/'))
+            });
+            if !inside {
+                let name = self.args.package.as_deref().unwrap_or_default();
+                d.hit(format!("outside the slice of --package {}", name), true);
+            }
+        }
+        if let Some(dir) = self
+            .submodules
+            .iter()
+            .find(|dir| rel.strip_prefix(dir.as_str()).is_some_and(|r| r.starts_with('/')))
+        {
+            d.hit(format!("inside submodule {} (--submodules skip)", dir), true);
+        }
+        if let (Some(list), Some(file)) = (&self.skip_list, &self.args.skip_from)
+            && list.contains(&rel)
+        {
+            d.hit(format!("listed in --skip-from {}", file.display()), true);
+        }
+        if !d.included && !thorough {
+            return d;
+        }
+
+        let forced: Vec<&str> = self.includes.hits(&rel).collect();
+        for p in &forced {
+            d.hit(format!("matched --include {}", p), false);
+        }
+        let overridden = if forced.is_empty() { "" } else { " (overridden by --include)" };
+        for p in self.excludes.hits(&rel) {
+            d.hit(format!("matched --exclude {}{}", p, overridden), forced.is_empty());
+        }
+        for p in self.builtin.hits(&rel) {
+            let category = exclude_category(p);
+            d.hit(format!("hit builtin exclude {} ({}){}", p, category, overridden), forced.is_empty());
+        }
+        // .treemergeignore rules take precedence over .ignore ones, and
+        // those over the gitignore ones, as in the scan
+        let ignore_rule = |path: &Path| {
+            let canonical = path.canonicalize().ok()?;
+            [&self.treemergeignores, &self.dot_ignores, &self.gitignores]
+                .into_iter()
+                .flatten()
+                .find_map(|ignores| ignores.rule(&canonical))
+        };
+        if (d.included || thorough)
+            && let Some((ignored, rule)) = ignore_rule(path)
+        {
+            let suffix = if ignored { overridden } else { "" };
+            d.hit(format!("{}{}", rule, suffix), ignored && forced.is_empty());
+        }
+        if !d.included && !thorough {
+            return d;
+        }
+
+        if !self.args.only.is_empty() {
+            let kind = lang::kind_for(path);
+            if !kind.is_some_and(|k| self.args.only.contains(&k)) {
+                let name = |k: &FileKind| k.to_possible_value().map(|v| v.get_name().to_string());
+                let wanted: Vec<String> = self.args.only.iter().filter_map(name).collect();
+                let is = kind.as_ref().and_then(name).unwrap_or_else(|| "unknown".into());
+                d.hit(format!("not --only {} (kind: {})", wanted.join(","), is), true);
+            }
+        }
+
+        // The rest is decided by the code the merge runs
+        #[cfg(all(unix, feature = "unix-filters"))]
+        let owned = |path: &Path| self.ownership.matches(path);
+        #[cfg(not(all(unix, feature = "unix-filters")))]
+        let owned = |_: &Path| true;
+        match found_verdict(path, self.args, owned).and_then(|()| section_verdict(path, self.args)) {
+            Ok(()) => {}
+            Err(reason) => d.hit(reason, true),
+        }
+
+        if d.included
+            && let Some(p) = forced.first()
+        {
+            d.reason = format!("matched --include {}", p);
+        }
+        d
+    }
+}
+
+/// Every file under `root` with its decision, sorted by relative path
+pub fn candidates(root: &Path, args: &Settings) -> Result<Vec<(String, PathBuf, Decision)>> {
+    let rules = Rules::new(root, args)?;
+    let mut entries: Vec<(String, PathBuf, Decision)> = WalkDir::new(root)
+        .follow_links(args.follow_symlinks)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            let decision = rules.decide(e.path(), false);
+            (rel_path(root, e.path()), e.into_path(), decision)
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+/// One line of a listing: a file, or a directory standing for the given
+/// number of files that share its decision
+struct Row<'a> {
+    path: PathBuf,
+    files: Option<usize>,
+    decision: &'a Decision,
+}
+
+/// Collapse directories whose files are all excluded for the same reason
+/// (`.git/`, `node_modules/`) into one row
+fn rows<'a>(root: &Path, entries: &'a [(String, PathBuf, Decision)]) -> Vec<Row<'a>> {
+    // For each directory: its file count and the exclusion shared by all
+    // of them (None once the files disagree or one is included)
+    let mut dirs: HashMap<&str, (usize, Option<&str>)> = HashMap::new();
+    for (rel, _, d) in entries {
+        for (i, _) in rel.match_indices('/') {
+            let reason = (!d.included).then_some(d.reason.as_str());
+            let e = dirs.entry(&rel[..i]).or_insert((0, reason));
+            e.0 += 1;
+            if e.1 != reason {
+                e.1 = None;
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut collapsed: Option<&str> = None;
+    for (rel, path, d) in entries {
+        if let Some(dir) = collapsed
+            && rel.strip_prefix(dir).is_some_and(|r| r.starts_with('/'))
+        {
+            continue;
+        }
+        let uniform = rel.match_indices('/').map(|(i, _)| &rel[..i]).find(|dir| {
+            dirs.get(dir)
+                .is_some_and(|(n, r)| *n > 1 && !d.included && *r == Some(d.reason.as_str()))
+        });
+        match uniform {
+            Some(dir) => {
+                out.push(Row {
+                    path: root.join(dir),
+                    files: Some(dirs[dir].0),
+                    decision: d,
+                });
+                collapsed = Some(dir);
+            }
+            None => out.push(Row {
+                path: path.clone(),
+                files: None,
+                decision: d,
+            }),
+        }
+    }
+    out
+}
+
+/// Print every file under `root` with the reason it would or would not be
+/// merged
+pub fn print(root: &Path, args: &Settings, shown: impl Fn(&Path) -> String, style: Style) -> Result<()> {
+    let entries = candidates(root, args)?;
+    let included = entries.iter().filter(|(_, _, d)| d.included).count();
+    println!(
+        "Explain. {} of {} candidates would be merged:",
+        included,
+        entries.len()
+    );
+    for row in rows(root, &entries) {
+        let d = row.decision;
+        let verdict = if d.included {
+            style.included("included")
+        } else {
+            style.excluded("excluded")
+        };
+        match row.files {
+            Some(n) => {
+                let reason = format!("({} files: {})", n, d.reason);
+                println!("  {}  {}/  {}", verdict, shown(&row.path), style.dim(&reason));
+            }
+            None => {
+                let reason = format!("({})", d.reason);
+                println!("  {}  {}  {}", verdict, shown(&row.path), style.dim(&reason));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Files under `root` the filters leave out, as `(path, reason)`
+///
+/// Uniformly excluded directories come as one entry, `dir/` with the
+/// file count in the reason.
+pub fn excluded(root: &Path, args: &Settings, shown: impl Fn(&Path) -> String) -> Result<Vec<(String, String)>> {
+    let entries = candidates(root, args)?;
+    let out = rows(root, &entries)
+        .into_iter()
+        .filter(|row| !row.decision.included)
+        .map(|row| match row.files {
+            Some(n) => (
+                format!("{}/", shown(&row.path)),
+                format!("{} ({} files)", row.decision.reason, n),
+            ),
+            None => (shown(&row.path), row.decision.reason.clone()),
+        })
+        .collect();
+    Ok(out)
+}
+
+/// `treemerge why`: trace one path through the filters, as a merge of `dir`
+/// with `options` would
+pub fn why(dir: &Path, path: &Path, options: &[String]) -> Result<()> {
+    let argv = ["treemerge".into(), dir.as_os_str().to_owned()]
+        .into_iter()
+        .chain(options.iter().map(Into::into));
+    let args: Settings = Args::parse_from(config::merge_argv(argv)?).into();
+
+    let file = if path.is_file() { path.to_path_buf() } else { dir.join(path) };
+    if !file.is_file() {
+        return Err(anyhow!("no such file: {}", path.display()));
+    }
+    let canonical_dir = dir.canonicalize()?;
+    let canonical = file.canonicalize()?;
+    let Ok(rel) = canonical.strip_prefix(&canonical_dir) else {
+        return Err(anyhow!("{} is not under {}", path.display(), dir.display()));
+    };
+    // Rules match paths as the walk produces them
+    let file = dir.join(rel);
+
+    let rules = Rules::new(dir, &args)?;
+    let d = rules.decide(&file, true);
+    println!("{}", rel_path(dir, &file));
+    if d.hits.is_empty() {
+        println!("  no rule applies");
+    }
+    for (i, hit) in d.hits.iter().enumerate() {
+        println!("  {}. {}", i + 1, hit);
+    }
+    let verdict = if d.included { "included" } else { "excluded" };
+    println!("{}: {}", verdict, d.reason);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn decide(root: &Path, file: &str, options: &[&str]) -> Decision {
+        let argv = ["treemerge", root.to_str().unwrap()].into_iter().chain(options.iter().copied());
+        let args: Settings = Args::try_parse_from(argv).unwrap().into();
+        Rules::new(root, &args).unwrap().decide(&root.join(file), false)
+    }
+
+    #[test]
+    fn excludes_and_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.md"), "a\n").unwrap();
+        fs::write(dir.path().join("empty.txt"), "").unwrap();
+        assert!(!decide(dir.path(), "a.md", &["-x", "*.md"]).included);
+        let d = decide(dir.path(), "a.md", &["-x", "*.md", "-i", "a.md"]);
+        assert!(d.included);
+        assert_eq!(d.reason, "matched --include a.md");
+        assert_eq!(decide(dir.path(), "empty.txt", &[]).reason, "empty file");
+    }
+
+    #[test]
+    fn why_needs_a_file_under_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree).unwrap();
+        fs::write(tree.join("a.txt"), "a\n").unwrap();
+        fs::write(dir.path().join("outside.txt"), "x\n").unwrap();
+        why(&tree, Path::new("a.txt"), &[]).unwrap();
+        assert!(why(&tree, Path::new("missing.txt"), &[]).is_err());
+        assert!(why(&tree, &dir.path().join("outside.txt"), &[]).is_err());
+    }
+
+    const POINTER: &str = "version https://git-lfs.github.com/spec/v1\n\
+        oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+        size 12345\n";
+
+    #[test]
+    fn lfs_pointers_as_the_merge_decides() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("big.bin"), POINTER).unwrap();
+        assert!(decide(dir.path(), "big.bin", &[]).included);
+        let d = decide(dir.path(), "big.bin", &["--lfs", "skip"]);
+        assert!(!d.included);
+        assert_eq!(d.reason, "git-lfs pointer");
+    }
+}
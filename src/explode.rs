@@ -0,0 +1,91 @@
+use crate::cli::OutputEncoding;
+use crate::encoding;
+use crate::template::TemplateFile;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Where the document for header path `path` goes under `dir`: the same
+/// relative path with `.md` appended
+fn target(dir: &Path, path: &str) -> PathBuf {
+    let mut out = dir.to_path_buf();
+    // Only plain components, so a label can never point outside `dir`
+    for c in Path::new(path).components() {
+        if let Component::Normal(part) = c {
+            out.push(part);
+        }
+    }
+    let mut name = out.file_name().unwrap_or_default().to_os_string();
+    name.push(".md");
+    out.set_file_name(name);
+    out
+}
+
+/// A backtick fence longer than any run of backticks in `text`
+pub fn fence(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// One markdown document: YAML front matter, a heading and the fenced body
+fn document(file: &TemplateFile) -> Result<String> {
+    // JSON strings are valid YAML scalars, whatever the path holds
+    let mut out = String::from("---\n");
+    out.push_str(&format!("path: {}\n", serde_json::to_string(&file.path)?));
+    if let Some(language) = file.language {
+        out.push_str(&format!("language: {}\n", serde_json::to_string(language)?));
+    }
+    out.push_str(&format!("size: {}\nlines: {}\n", file.size, file.lines));
+    if let Some(commit) = &file.commit {
+        out.push_str(&format!("commit: {}\n", commit));
+    }
+    if !file.notes.is_empty() {
+        out.push_str(&format!("notes: {}\n", serde_json::to_string(&file.notes)?));
+    }
+    out.push_str("---\n\n");
+
+    let fence = fence(&file.content);
+    out.push_str(&format!("# {}\n\n", file.path));
+    out.push_str(&format!("{}{}\n", fence, file.language.unwrap_or_default()));
+    out.push_str(&file.content);
+    if !file.content.is_empty() && !file.content.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&format!("{}\n", fence));
+    Ok(out)
+}
+
+/// Write one markdown document per file into `dir`, mirroring the tree
+pub fn write(dir: &Path, files: &[TemplateFile], encoding: OutputEncoding) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for file in files {
+        let path = target(dir, &file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("cannot create {}", parent.display()))?;
+        }
+        fs::write(&path, encoding::encode(encoding, &document(file)?))
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_mirror_the_tree() {
+        let dir = Path::new("out");
+        assert_eq!(target(dir, "src/main.rs"), Path::new("out/src/main.rs.md"));
+        assert_eq!(target(dir, "../../etc/passwd"), Path::new("out/etc/passwd.md"));
+        assert_eq!(target(dir, "/abs/a.txt"), Path::new("out/abs/a.txt.md"));
+        assert_eq!(fence("no ticks"), "```");
+        assert_eq!(fence("a ```` b ` c"), "`````");
+    }
+}
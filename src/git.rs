@@ -0,0 +1,527 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset};
+use git2::{Delta, Patch, Repository, RevparseMode, Sort, StatusOptions};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Revision information for the repository containing the root
+pub struct RepoInfo {
+    pub remote: Option<String>,
+    pub branch: Option<String>,
+    pub commit: Option<String>,
+    pub commit_date: Option<String>,
+    pub dirty: bool,
+}
+
+/// Format a git timestamp as ISO-8601 in the committer's own offset
+pub fn format_time(time: git2::Time) -> String {
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|tz| DateTime::from_timestamp(time.seconds(), 0).map(|t| t.with_timezone(&tz)))
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| time.seconds().to_string())
+}
+
+/// The day of a git timestamp, `YYYY-MM-DD` in its own offset
+fn format_date(time: git2::Time) -> String {
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|tz| DateTime::from_timestamp(time.seconds(), 0).map(|t| t.with_timezone(&tz)))
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| time.seconds().to_string())
+}
+
+/// Open the repository containing `root`, if any
+pub fn open(root: &Path) -> Option<Repository> {
+    Repository::discover(root).ok()
+}
+
+/// Submodules of the repository containing `root` that lie below it, as
+/// root-relative paths, each with whether it is checked out
+pub fn submodules(root: &Path) -> Vec<(String, bool)> {
+    let Some(repo) = open(root) else {
+        return Vec::new();
+    };
+    let (Some(workdir), Ok(root)) = (repo.workdir(), root.canonicalize()) else {
+        return Vec::new();
+    };
+    let workdir = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+    let Ok(found) = repo.submodules() else {
+        return Vec::new();
+    };
+    found
+        .iter()
+        .filter_map(|sm| {
+            let dir = workdir.join(sm.path());
+            let rel = dir.strip_prefix(&root).ok()?.to_string_lossy().replace('\\', "/");
+            Some((rel, dir.join(".git").exists()))
+        })
+        .collect()
+}
+
+/// Collect remote, branch, HEAD commit and dirty state
+pub fn repo_info(repo: &Repository) -> Result<RepoInfo> {
+    let remote = repo
+        .find_remote("origin")
+        .ok()
+        .or_else(|| {
+            let names = repo.remotes().ok()?;
+            let first = names.get(0)?.to_owned();
+            repo.find_remote(&first).ok()
+        })
+        .and_then(|r| r.url().map(str::to_owned));
+
+    let head = repo.head().ok();
+    let branch = head.as_ref().map(|h| {
+        if h.is_branch() {
+            h.shorthand().unwrap_or("HEAD").to_owned()
+        } else {
+            "HEAD (detached)".to_owned()
+        }
+    });
+    let commit = head.as_ref().and_then(|h| h.peel_to_commit().ok());
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).include_ignored(false);
+    let dirty = !repo.statuses(Some(&mut opts))?.is_empty();
+
+    Ok(RepoInfo {
+        remote,
+        branch,
+        commit_date: commit.as_ref().map(|c| format_time(c.time())),
+        commit: commit.map(|c| c.id().to_string()),
+        dirty,
+    })
+}
+
+/// Write the repository preamble at the top of the output
+pub fn write_preamble<W: Write>(w: &mut W, info: &RepoInfo) -> Result<()> {
+    let unknown = "(none)";
+    writeln!(w, "Repository metadata")?;
+    writeln!(w, "  remote: {}", info.remote.as_deref().unwrap_or(unknown))?;
+    writeln!(w, "  branch: {}", info.branch.as_deref().unwrap_or(unknown))?;
+    writeln!(w, "  commit: {}", info.commit.as_deref().unwrap_or(unknown))?;
+    writeln!(w, "  date:   {}", info.commit_date.as_deref().unwrap_or(unknown))?;
+    writeln!(
+        w,
+        "  dirty:  {}",
+        if info.dirty { "yes (uncommitted changes)" } else { "no" }
+    )?;
+    Ok(())
+}
+
+/// Last commit that touched a file
+pub struct FileCommit {
+    pub id: String,
+    pub author: String,
+    pub date: String,
+}
+
+impl FileCommit {
+    /// Compact form used in file headers
+    pub fn annotation(&self) -> String {
+        format!("{} {} {}", &self.id[..self.id.len().min(10)], self.author, self.date)
+    }
+}
+
+/// Path of `path` relative to the repository working directory
+pub fn repo_relative(repo: &Repository, path: &Path) -> Option<PathBuf> {
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    path.strip_prefix(&workdir).ok().map(Path::to_path_buf)
+}
+
+/// Find the most recent commit touching each of `wanted` (repo-relative paths)
+///
+/// Walks history once from HEAD, diffing each commit against its first
+/// parent, and stops as soon as every wanted path has been attributed.
+/// Paths not in HEAD (untracked files) are left out before the walk, so
+/// they do not keep it going to the first commit.
+pub fn last_commits(
+    repo: &Repository,
+    wanted: &HashSet<PathBuf>,
+) -> Result<HashMap<PathBuf, FileCommit>> {
+    let mut found = HashMap::new();
+
+    let Ok(head) = repo.head().and_then(|h| h.peel_to_tree()) else {
+        // No commits yet
+        return Ok(found);
+    };
+    let tracked: HashSet<&Path> = wanted.iter().map(PathBuf::as_path).filter(|p| head.get_path(p).is_ok()).collect();
+    if tracked.is_empty() {
+        return Ok(found);
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(Sort::TIME)?;
+
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path() else {
+                continue;
+            };
+            if tracked.contains(path) && !found.contains_key(path) {
+                let author = commit.author();
+                found.insert(
+                    path.to_path_buf(),
+                    FileCommit {
+                        id: commit.id().to_string(),
+                        author: author.name().unwrap_or("unknown").to_owned(),
+                        date: format_date(commit.time()),
+                    },
+                );
+            }
+        }
+
+        if found.len() == tracked.len() {
+            break;
+        }
+    }
+
+    Ok(found)
+}
+
+/// Line ownership summary derived from `git blame`
+pub struct BlameSummary {
+    /// Authors ordered by number of lines, largest first
+    pub authors: Vec<(String, usize)>,
+    /// Line counts by age bucket: <1 month, <1 year, older
+    pub ages: [usize; 3],
+    pub total: usize,
+}
+
+impl BlameSummary {
+    /// Compact form used in file headers
+    pub fn annotation(&self) -> String {
+        let pct = |n: usize| n * 100 / self.total.max(1);
+        let authors: Vec<String> = self
+            .authors
+            .iter()
+            .take(3)
+            .map(|(name, n)| format!("{} {}%", name, pct(*n)))
+            .collect();
+        format!(
+            "blame: {}; age <1m {}%, <1y {}%, older {}%",
+            authors.join(", "),
+            pct(self.ages[0]),
+            pct(self.ages[1]),
+            pct(self.ages[2])
+        )
+    }
+}
+
+/// Summarize blame for a repo-relative path at HEAD
+pub fn blame_summary(repo: &Repository, path: &Path, now: i64) -> Result<BlameSummary> {
+    const MONTH: i64 = 30 * 24 * 3600;
+    const YEAR: i64 = 365 * 24 * 3600;
+
+    let blame = repo.blame_file(path, None)?;
+    let mut by_author: HashMap<String, usize> = HashMap::new();
+    let mut ages = [0usize; 3];
+    let mut total = 0;
+
+    for hunk in blame.iter() {
+        let lines = hunk.lines_in_hunk();
+        let sig = hunk.final_signature();
+        let name = sig.name().unwrap_or("unknown").to_owned();
+        *by_author.entry(name).or_default() += lines;
+
+        let age = now - sig.when().seconds();
+        let bucket = match age {
+            a if a < MONTH => 0,
+            a if a < YEAR => 1,
+            _ => 2,
+        };
+        ages[bucket] += lines;
+        total += lines;
+    }
+
+    let mut authors: Vec<(String, usize)> = by_author.into_iter().collect();
+    authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(BlameSummary { authors, ages, total })
+}
+
+/// Unified diff of one file within a revision range
+pub struct FileDiff {
+    /// Repo-relative path (post-image path, or pre-image for deletions)
+    pub path: PathBuf,
+    pub status: Delta,
+    pub patch: String,
+    /// Post-image content, when it exists and is text
+    pub post_image: Option<String>,
+}
+
+/// Diff a revision range: `A..B`, `A...B` (from the merge base), or a single
+/// revision compared against the working tree
+pub fn diff_range(repo: &Repository, range: &str) -> Result<Vec<FileDiff>> {
+    let spec = repo
+        .revparse(range)
+        .with_context(|| format!("invalid revision range: {}", range))?;
+
+    let from = spec
+        .from()
+        .ok_or_else(|| anyhow!("invalid revision range: {}", range))?
+        .peel_to_commit()?;
+    let to = spec.to().map(|o| o.peel_to_commit()).transpose()?;
+
+    let base = if spec.mode().contains(RevparseMode::MERGE_BASE) {
+        let to = to.as_ref().ok_or_else(|| anyhow!("invalid revision range: {}", range))?;
+        repo.find_commit(repo.merge_base(from.id(), to.id())?)?
+    } else {
+        from
+    };
+
+    let old_tree = base.tree()?;
+    let new_tree = to.as_ref().map(|c| c.tree()).transpose()?;
+    let diff = match &new_tree {
+        Some(tree) => repo.diff_tree_to_tree(Some(&old_tree), Some(tree), None)?,
+        None => repo.diff_tree_to_workdir_with_index(Some(&old_tree), None)?,
+    };
+
+    let workdir = repo.workdir().map(Path::to_path_buf);
+    let mut diffs = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(mut patch) = Patch::from_diff(&diff, idx)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        if delta.flags().is_binary() {
+            continue;
+        }
+        let status = delta.status();
+        let Some(path) = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(Path::to_path_buf)
+        else {
+            continue;
+        };
+
+        let post_image = match (status, &new_tree) {
+            (Delta::Deleted, _) => None,
+            (_, Some(_)) => repo
+                .find_blob(delta.new_file().id())
+                .ok()
+                .and_then(|b| String::from_utf8(b.content().to_vec()).ok()),
+            (_, None) => workdir
+                .as_ref()
+                .and_then(|w| std::fs::read_to_string(w.join(&path)).ok()),
+        };
+
+        let buf = patch.to_buf()?;
+        diffs.push(FileDiff {
+            path,
+            status,
+            patch: String::from_utf8_lossy(&buf).into_owned(),
+            post_image,
+        });
+    }
+
+    Ok(diffs)
+}
+
+/// Working-tree state of every file that differs from HEAD, keyed by repo-relative path
+pub fn file_states(repo: &Repository) -> Result<HashMap<PathBuf, &'static str>> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+
+    let mut states = HashMap::new();
+    for entry in repo.statuses(Some(&mut opts))?.iter() {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        let s = entry.status();
+        let label = if s.is_conflicted() {
+            "conflicted"
+        } else if s.is_wt_new() {
+            "untracked"
+        } else if s.is_index_new() {
+            "added"
+        } else if s.is_index_renamed() || s.is_wt_renamed() {
+            "renamed"
+        } else if s.is_index_modified()
+            || s.is_wt_modified()
+            || s.is_index_typechange()
+            || s.is_wt_typechange()
+        {
+            "modified"
+        } else {
+            continue;
+        };
+        states.insert(PathBuf::from(path), label);
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Write `files` under the work tree of `repo` and commit them at `time`
+    fn commit(repo: &Repository, files: &[(&str, &str)], time: i64) -> git2::Oid {
+        let workdir = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (path, body) in files {
+            fs::create_dir_all(workdir.join(path).parent().unwrap()).unwrap();
+            fs::write(workdir.join(path), body).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let who = git2::Signature::new("Jo", "jo@example.org", &git2::Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &who, &who, "change", &tree, &parents).unwrap()
+    }
+
+    #[test]
+    fn repository_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.remote("origin", "https://example.org/jo/project.git").unwrap();
+        let id = commit(&repo, &[("a.txt", "a")], 1_700_000_000);
+        let info = repo_info(&repo).unwrap();
+        assert_eq!(info.remote.as_deref(), Some("https://example.org/jo/project.git"));
+        assert_eq!(info.commit, Some(id.to_string()));
+        assert_eq!(info.commit_date.as_deref(), Some("2023-11-14T22:13:20+00:00"));
+        assert!(!info.dirty);
+
+        fs::write(dir.path().join("new.txt"), "b").unwrap();
+        let info = repo_info(&repo).unwrap();
+        assert!(info.dirty);
+        let mut out = Vec::new();
+        write_preamble(&mut out, &info).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("Repository metadata\n  remote: https://example.org/jo/project.git\n"));
+        assert!(out.ends_with("  dirty:  yes (uncommitted changes)\n"));
+    }
+
+    #[test]
+    fn last_commit_of_each_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, &[("a.txt", "a"), ("src/b.rs", "b")], 1_600_000_000);
+        let second = commit(&repo, &[("a.txt", "a2")], 1_700_000_000);
+
+        let wanted: HashSet<PathBuf> = ["a.txt", "src/b.rs"].map(PathBuf::from).into();
+        let found = last_commits(&repo, &wanted).unwrap();
+        assert_eq!(found[Path::new("a.txt")].id, second.to_string());
+        assert_eq!(found[Path::new("src/b.rs")].id, first.to_string());
+        let annotation = found[Path::new("src/b.rs")].annotation();
+        assert_eq!(annotation, format!("{} Jo 2020-09-13", &first.to_string()[..10]));
+        assert_eq!(repo_relative(&repo, &dir.path().join("src/b.rs")), Some(PathBuf::from("src/b.rs")));
+    }
+
+    #[test]
+    fn blame_by_author_and_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let day = 24 * 3600;
+        commit(&repo, &[("a.txt", "1\n2\n3\n")], 1_600_000_000);
+        commit(&repo, &[("a.txt", "1\n2\n3\n4\n5\n6\n7\n8\n")], 1_700_000_000);
+        commit(&repo, &[("a.txt", "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n")], 1_700_000_000 + 300 * day);
+
+        let summary = blame_summary(&repo, Path::new("a.txt"), 1_700_000_000 + 310 * day).unwrap();
+        assert_eq!(summary.total, 10);
+        assert_eq!(summary.authors, [("Jo".to_string(), 10)]);
+        assert_eq!(summary.ages, [2, 5, 3]);
+        assert_eq!(summary.annotation(), "blame: Jo 100%; age <1m 20%, <1y 50%, older 30%");
+    }
+
+    #[test]
+    fn diffs_of_a_range_and_the_work_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "1\n2\n"), ("b.txt", "b\n")], 1_600_000_000);
+        commit(&repo, &[("a.txt", "1\ntwo\n"), ("c.txt", "c\n")], 1_600_000_100);
+
+        let diffs = diff_range(&repo, "HEAD~1..HEAD").unwrap();
+        let found: Vec<(&Path, Delta)> = diffs.iter().map(|d| (d.path.as_path(), d.status)).collect();
+        assert_eq!(found, [(Path::new("a.txt"), Delta::Modified), (Path::new("c.txt"), Delta::Added)]);
+        assert!(diffs[0].patch.contains("-2\n+two\n"));
+        assert_eq!(diffs[0].post_image.as_deref(), Some("1\ntwo\n"));
+
+        fs::write(dir.path().join("b.txt"), "changed\n").unwrap();
+        let diffs = diff_range(&repo, "HEAD").unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].post_image.as_deref(), Some("changed\n"));
+        assert!(diff_range(&repo, "nope..HEAD").is_err());
+    }
+
+    #[test]
+    fn working_tree_states() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("a.txt", "a\n"), ("b.txt", "b\n")], 1_600_000_000);
+        fs::write(dir.path().join("a.txt"), "changed\n").unwrap();
+        fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        let mut index = repo.index().unwrap();
+        fs::write(dir.path().join("staged.txt"), "staged\n").unwrap();
+        index.add_path(Path::new("staged.txt")).unwrap();
+        index.write().unwrap();
+
+        let mut states: Vec<(PathBuf, &str)> = file_states(&repo).unwrap().into_iter().collect();
+        states.sort();
+        assert_eq!(
+            states,
+            [
+                (PathBuf::from("a.txt"), "modified"),
+                (PathBuf::from("new.txt"), "untracked"),
+                (PathBuf::from("staged.txt"), "added"),
+            ]
+        );
+    }
+
+    #[test]
+    fn submodules_below_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let modules = "[submodule \"lib\"]\n\tpath = vendor/lib\n\turl = https://example.org/lib.git\n\
+                       [submodule \"docs\"]\n\tpath = docs\n\turl = https://example.org/docs.git\n";
+        commit(&repo, &[(".gitmodules", modules)], 1_600_000_000);
+        fs::create_dir_all(dir.path().join("vendor/lib")).unwrap();
+        fs::write(dir.path().join("vendor/lib/.git"), "gitdir: ../../.git/modules/lib\n").unwrap();
+
+        let mut found = submodules(dir.path());
+        found.sort();
+        assert_eq!(found, [("docs".to_string(), false), ("vendor/lib".to_string(), true)]);
+        assert_eq!(submodules(&dir.path().join("vendor")), [("lib".to_string(), true)]);
+        assert!(submodules(&dir.path().join("nowhere")).is_empty());
+    }
+
+    #[test]
+    fn dates_of_any_timestamp() {
+        assert_eq!(format_date(git2::Time::new(0, 0)), "1970-01-01");
+        assert_eq!(format_date(git2::Time::new(86_399, 60)), "1970-01-02");
+        // Out of chrono's range: the seconds, however short
+        assert_eq!(format_date(git2::Time::new(i64::MAX, 0)), i64::MAX.to_string());
+        assert_eq!(format_date(git2::Time::new(5, 24 * 60)), "5");
+    }
+
+    #[test]
+    fn untracked_files_have_no_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("kept.txt", "a")], 1_700_000_000);
+        fs::write(dir.path().join("new.txt"), "b").unwrap();
+
+        let wanted: HashSet<PathBuf> = ["kept.txt", "new.txt"].map(PathBuf::from).into();
+        let found = last_commits(&repo, &wanted).unwrap();
+        assert_eq!(found.len(), 1);
+        let commit = &found[Path::new("kept.txt")];
+        assert_eq!((commit.author.as_str(), commit.date.as_str()), ("Jo", "2023-11-14"));
+    }
+}
@@ -0,0 +1,223 @@
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+/// How an import names its target
+enum Target {
+    /// Path relative to the importing file (without extension guessing)
+    Relative(PathBuf),
+    /// Path suffix to look up anywhere in the tree (e.g. `a/b` from `a.b`)
+    Suffix(PathBuf),
+}
+
+static RUST_MOD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_][A-Za-z0-9_]*)\s*;").unwrap());
+static RUST_USE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+(crate|super|self)::([A-Za-z0-9_:]+)").unwrap());
+static PY_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*import\s+([A-Za-z0-9_.]+)").unwrap());
+static PY_FROM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*from\s+(\.*)([A-Za-z0-9_.]*)\s+import\s+([A-Za-z0-9_, ]+)").unwrap());
+static JS_SPEC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:\bfrom\s+|\bimport\s*\(?\s*|\brequire\s*\(\s*)["'](\.{1,2}/[^"']+)["']"#).unwrap()
+});
+static C_INCLUDE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\s*#\s*include\s+"([^"]+)""#).unwrap());
+static JAVA_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*import\s+(?:static\s+)?([A-Za-z0-9_.]+)\s*;?").unwrap());
+
+/// Lexically normalize a path, dropping `.` and resolving `..`
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Extract the import targets of a file
+fn targets(lang: &str, path: &Path, content: &str) -> Vec<Target> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let mut out = Vec::new();
+
+    for line in content.lines() {
+        match lang {
+            "rust" => {
+                if let Some(c) = RUST_MOD.captures(line) {
+                    let name = &c[1];
+                    let base = if matches!(stem, "mod" | "lib" | "main") {
+                        dir.to_path_buf()
+                    } else {
+                        dir.join(stem)
+                    };
+                    out.push(Target::Relative(base.join(name)));
+                } else if let Some(c) = RUST_USE.captures(line) {
+                    let parts: Vec<&str> = c[2].split("::").filter(|p| !p.is_empty()).collect();
+                    // Every prefix could be the module file (`a`, `a/b`, ...)
+                    for n in 1..=parts.len() {
+                        let rel: PathBuf = parts[..n].iter().collect();
+                        match &c[1] {
+                            "crate" => out.push(Target::Suffix(Path::new("src").join(&rel))),
+                            "super" => out.push(Target::Relative(dir.join(&rel))),
+                            _ => out.push(Target::Relative(dir.join(stem).join(&rel))),
+                        }
+                    }
+                }
+            }
+            "python" => {
+                if let Some(c) = PY_FROM.captures(line) {
+                    let dots = c[1].len();
+                    let module: PathBuf = c[2].split('.').filter(|p| !p.is_empty()).collect();
+                    let names: Vec<&str> = c[3].split(',').map(|n| n.split_whitespace().next().unwrap_or("")).collect();
+                    if dots > 0 {
+                        let mut base = dir.to_path_buf();
+                        for _ in 1..dots {
+                            base.push("..");
+                        }
+                        let base = base.join(&module);
+                        out.push(Target::Relative(base.clone()));
+                        for name in names.iter().filter(|n| !n.is_empty()) {
+                            out.push(Target::Relative(base.join(name)));
+                        }
+                    } else {
+                        out.push(Target::Suffix(module.clone()));
+                        for name in names.iter().filter(|n| !n.is_empty()) {
+                            out.push(Target::Suffix(module.join(name)));
+                        }
+                    }
+                } else if let Some(c) = PY_IMPORT.captures(line) {
+                    out.push(Target::Suffix(c[1].split('.').collect()));
+                }
+            }
+            "javascript" | "typescript" | "vue" | "svelte" => {
+                for c in JS_SPEC.captures_iter(line) {
+                    out.push(Target::Relative(dir.join(&c[1])));
+                }
+            }
+            "c" | "cpp" => {
+                if let Some(c) = C_INCLUDE.captures(line) {
+                    out.push(Target::Relative(dir.join(&c[1])));
+                    out.push(Target::Suffix(PathBuf::from(&c[1])));
+                }
+            }
+            "java" | "kotlin" | "scala" => {
+                if let Some(c) = JAVA_IMPORT.captures(line) {
+                    out.push(Target::Suffix(c[1].split('.').collect()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out
+}
+
+/// Extensions tried when an import omits them
+fn candidates(lang: &str, base: &Path) -> Vec<PathBuf> {
+    let with = |suffix: &str| PathBuf::from(format!("{}{}", base.display(), suffix));
+    match lang {
+        "rust" => vec![with(".rs"), base.join("mod.rs")],
+        "python" => vec![with(".py"), base.join("__init__.py")],
+        "javascript" | "typescript" | "vue" | "svelte" => {
+            let mut v = vec![base.to_path_buf()];
+            for ext in [".ts", ".tsx", ".js", ".jsx", ".mjs", ".cjs", ".vue", ".svelte"] {
+                v.push(with(ext));
+            }
+            for index in ["index.ts", "index.tsx", "index.js", "index.jsx"] {
+                v.push(base.join(index));
+            }
+            v
+        }
+        "java" => vec![with(".java")],
+        "kotlin" => vec![with(".kt"), with(".java")],
+        "scala" => vec![with(".scala"), with(".java")],
+        _ => vec![base.to_path_buf()],
+    }
+}
+
+/// Index of scanned files used to resolve imports
+pub struct ImportIndex {
+    /// Normalized path -> path as scanned
+    by_path: HashMap<PathBuf, PathBuf>,
+}
+
+impl ImportIndex {
+    pub fn new(files: &[PathBuf]) -> Self {
+        let by_path = files.iter().map(|f| (normalize(f), f.clone())).collect();
+        ImportIndex { by_path }
+    }
+
+    fn lookup_suffix(&self, suffix: &Path, out: &mut HashSet<PathBuf>) {
+        for (norm, original) in &self.by_path {
+            if norm.ends_with(suffix) {
+                out.insert(original.clone());
+            }
+        }
+    }
+
+    /// Files from the index that `path` imports
+    pub fn resolve(&self, lang: &str, path: &Path, content: &str) -> Vec<PathBuf> {
+        let mut found = HashSet::new();
+        for target in targets(lang, path, content) {
+            match target {
+                Target::Relative(base) => {
+                    for cand in candidates(lang, &base) {
+                        if let Some(original) = self.by_path.get(&normalize(&cand)) {
+                            found.insert(original.clone());
+                        }
+                    }
+                }
+                Target::Suffix(base) => {
+                    for cand in candidates(lang, &base) {
+                        self.lookup_suffix(&cand, &mut found);
+                    }
+                }
+            }
+        }
+        found.remove(path);
+
+        let mut found: Vec<PathBuf> = found.into_iter().collect();
+        found.sort();
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_resolves_dots() {
+        assert_eq!(normalize(Path::new("./a/b/../c")), Path::new("a/c"));
+        assert_eq!(normalize(Path::new("../a")), Path::new("../a"));
+    }
+
+    #[test]
+    fn imports_resolve_to_scanned_files() {
+        let files: Vec<PathBuf> = ["src/main.rs", "src/cli.rs", "src/merge/mod.rs", "app/util.py", "app/__init__.py", "web/lib.ts", "web/index.ts"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let index = ImportIndex::new(&files);
+
+        let rust = index.resolve("rust", Path::new("src/main.rs"), "mod cli;\nmod merge;\nuse crate::cli::Args;\n");
+        assert_eq!(rust, [PathBuf::from("src/cli.rs"), PathBuf::from("src/merge/mod.rs")]);
+
+        let py = index.resolve("python", Path::new("app/run.py"), "from . import util\nimport os\n");
+        assert_eq!(py, [PathBuf::from("app/__init__.py"), PathBuf::from("app/util.py")]);
+
+        let js = index.resolve("typescript", Path::new("web/index.ts"), "import { f } from './lib';\n");
+        assert_eq!(js, [PathBuf::from("web/lib.ts")]);
+        assert!(index.resolve("go", Path::new("main.go"), "import \"fmt\"\n").is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::cli::FileKind;
+use crate::config;
+use std::path::Path;
+
+/// Classify a file by extension (or well-known file name)
+///
+/// Extensions mapped in the user config (`languages`, then `ext_aliases`)
+/// take precedence.
+pub fn language_for(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        let ext = ext.to_ascii_lowercase();
+        let config = config::get();
+        if let Some(lang) = config.languages.get(&ext).or_else(|| config.ext_aliases.get(&ext)) {
+            return Some(lang);
+        }
+    }
+    match name {
+        "Makefile" | "makefile" | "GNUmakefile" => return Some("make"),
+        "Dockerfile" => return Some("dockerfile"),
+        "CMakeLists.txt" => return Some("cmake"),
+        _ => {}
+    }
+
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "mts" | "cts" | "tsx" => "typescript",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "cpp",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "swift" => "swift",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "r" => "r",
+        "jl" => "julia",
+        "hs" => "haskell",
+        "ml" | "mli" => "ocaml",
+        "ex" | "exs" => "elixir",
+        "erl" => "erlang",
+        "lua" => "lua",
+        "pl" | "pm" => "perl",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "nix" => "nix",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "md" | "markdown" => "markdown",
+        "rst" => "rst",
+        "adoc" => "asciidoc",
+        "tex" => "latex",
+        "qmd" | "rmd" => "markdown",
+        "txt" => "text",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "json" => "json",
+        "ini" | "cfg" => "ini",
+        "xml" => "xml",
+        "proto" => "protobuf",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+/// Whether a file is code, docs or config, for `--only`; None for files of
+/// no known language
+pub fn kind_for(path: &Path) -> Option<FileKind> {
+    // README, CHANGELOG.old, ... carry no telling extension
+    const DOC_NAMES: &[&str] = &["README", "CHANGELOG", "CHANGES", "NEWS", "CONTRIBUTING", "AUTHORS", "HISTORY"];
+    let Some(lang) = language_for(path) else {
+        let name = path.file_name()?.to_str()?;
+        let is_doc = DOC_NAMES
+            .iter()
+            .any(|d| name.strip_prefix(d).is_some_and(|r| r.is_empty() || r.starts_with('.')));
+        return is_doc.then_some(FileKind::Docs);
+    };
+    Some(match lang {
+        "markdown" | "rst" | "asciidoc" | "latex" | "text" => FileKind::Docs,
+        "toml" | "yaml" | "json" | "ini" | "xml" => FileKind::Config,
+        _ => FileKind::Code,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds() {
+        let kind = |path: &str| kind_for(Path::new(path));
+        assert_eq!(kind("src/main.rs"), Some(FileKind::Code));
+        assert_eq!(kind("docs/guide.md"), Some(FileKind::Docs));
+        assert_eq!(kind("README"), Some(FileKind::Docs));
+        assert_eq!(kind("CHANGELOG.old"), Some(FileKind::Docs));
+        assert_eq!(kind("READMEFIRST"), None);
+        assert_eq!(kind("Cargo.toml"), Some(FileKind::Config));
+        assert_eq!(kind("data.bin"), None);
+    }
+}
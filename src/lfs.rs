@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Result};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Pointer files are tiny; anything larger is real content
+const MAX_POINTER_SIZE: u64 = 1024;
+
+/// Parsed Git LFS pointer file
+pub struct Pointer {
+    pub oid: String,
+    pub size: u64,
+    raw: Vec<u8>,
+}
+
+/// Recognize a Git LFS pointer (spec v1) without reading large files
+pub fn parse_pointer(path: &Path) -> Option<Pointer> {
+    if fs::metadata(path).ok()?.len() > MAX_POINTER_SIZE {
+        return None;
+    }
+    let mut raw = Vec::new();
+    File::open(path).ok()?.read_to_end(&mut raw).ok()?;
+    let text = std::str::from_utf8(&raw).ok()?;
+
+    let mut lines = text.lines();
+    if !lines.next()?.starts_with("version https://git-lfs.github.com/spec/") {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(v) = line.strip_prefix("oid ") {
+            oid = Some(v.to_owned());
+        } else if let Some(v) = line.strip_prefix("size ") {
+            size = v.trim().parse().ok();
+        }
+    }
+
+    Some(Pointer {
+        oid: oid?,
+        size: size?,
+        raw,
+    })
+}
+
+/// Fetch the real content behind a pointer via `git lfs smudge`
+pub fn smudge(path: &Path, pointer: &Pointer) -> Result<Vec<u8>> {
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut child = Command::new("git")
+        .args(["lfs", "smudge"])
+        .arg(path.file_name().unwrap_or_default())
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("cannot run git lfs: {}", e))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("cannot write to git lfs"))?
+        .write_all(&pointer.raw)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!("git lfs smudge failed for {}", path.display()));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointers_are_recognized() {
+        let dir = tempfile::tempdir().unwrap();
+        let pointer = dir.path().join("model.bin");
+        let oid = "sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+        fs::write(&pointer, format!("version https://git-lfs.github.com/spec/v1\noid {oid}\nsize 12345\n")).unwrap();
+        let parsed = parse_pointer(&pointer).unwrap();
+        assert_eq!((parsed.oid.as_str(), parsed.size), (oid, 12345));
+
+        let plain = dir.path().join("plain.txt");
+        fs::write(&plain, "version https://example.org\n").unwrap();
+        assert!(parse_pointer(&plain).is_none());
+        fs::write(&plain, "version https://git-lfs.github.com/spec/v1\nsize 1\n").unwrap();
+        assert!(parse_pointer(&plain).is_none());
+    }
+}
@@ -0,0 +1,152 @@
+//! Concatenate the text files of a directory tree into one document
+//!
+//! The `treemerge` binary is a thin wrapper over [`run_cli`]. To merge from
+//! other code, set up a merge with [`MergeOptions`] and write it with
+//! [`Merger::merge_to_writer`]:
+//!
+//! ```no_run
+//! use treemerge::{HeaderStyle, MergeOptions};
+//!
+//! let merger = MergeOptions::new("docs")
+//!     .extension("md")
+//!     .exclude("drafts/**")
+//!     .header_style(HeaderStyle::Plain)
+//!     .build()?;
+//! let mut out = Vec::new();
+//! merger.merge_to_writer(&mut out)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod batch;
+mod capture;
+mod checkpoint;
+mod chunks;
+mod cli;
+mod config;
+mod depgraph;
+mod deps;
+mod encoding;
+mod explain;
+mod explode;
+mod git;
+mod imports;
+mod interrupt;
+mod lang;
+mod lfs;
+mod manifest;
+mod merge;
+mod open;
+mod options;
+mod order;
+mod outline;
+mod pii;
+mod priority;
+mod previous;
+mod progress;
+mod prompts;
+mod ranges;
+mod remote;
+mod repos;
+mod report;
+mod restore;
+mod retry;
+mod sample;
+mod secrets;
+mod selfupdate;
+mod settings;
+mod skip;
+mod style;
+mod suggest;
+mod structured;
+mod summarize;
+mod template;
+mod throttle;
+mod tokens;
+mod transform;
+mod tree;
+#[cfg(all(unix, feature = "unix-filters"))]
+mod unix;
+mod unmerge;
+mod upload;
+mod watch;
+mod workspace;
+
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+use cli::{Args, Command, ConfigCommand};
+use std::ffi::OsString;
+use std::io;
+use std::process;
+
+pub use cli::{HeaderStyle, OutputEncoding, OutputFormat};
+pub use options::{MergeOptions, Merger};
+
+/// Run the command line `argv` (program name first) as the binary does,
+/// exiting with status 130 when a merge is interrupted and quietly when
+/// the reader of its stdout goes away
+pub fn run_cli(argv: impl IntoIterator<Item = OsString>) -> Result<()> {
+    let raw = cli::expand_response_files(argv)?;
+    let subcommand = raw.get(1).and_then(|a| a.to_str()).filter(|a| {
+        Args::command().get_subcommands().any(|c| c.get_name() == *a)
+    });
+    // `config` must work on a broken config file
+    if subcommand != Some("config") && !raw.iter().any(|a| a == "--no-config") {
+        config::init(config::load()?);
+    }
+    // Subcommands take no merge options (`why` and `suggest-ignore` apply
+    // the defaults to the ones they are given)
+    let is_subcommand = subcommand.is_some();
+    let argv = if is_subcommand {
+        raw.clone()
+    } else {
+        config::merge_argv(raw.clone())?
+    };
+    let mut args = Args::parse_from(argv);
+    let result = match args.command.take() {
+        Some(Command::Why { dir, path, options }) => explain::why(&dir, &path, &options),
+        Some(Command::SuggestIgnore { large, dir, options }) => {
+            suggest::suggest_ignore(&dir, large, &options)
+        }
+        Some(Command::SelfUpdate { check }) => selfupdate::self_update(check),
+        Some(Command::Unmerge { file, out_dir, force, manifest }) => unmerge::unmerge(&file, &out_dir, force, manifest.as_deref()),
+        Some(Command::Batch { file, report, fail_fast }) => {
+            interrupt::install();
+            batch::run(&file, report.as_deref(), fail_fast)
+        }
+        Some(Command::Config { action: ConfigCommand::Schema }) => {
+            print!("{}", config::SCHEMA);
+            Ok(())
+        }
+        Some(Command::Config { action: ConfigCommand::Path }) => {
+            match config::config_path() {
+                Some(path) => println!("{}", path.display()),
+                None => println!("no configuration directory (HOME is not set)"),
+            }
+            Ok(())
+        }
+        // Reads the configuration again for every run
+        None if args.watch => {
+            interrupt::install();
+            watch::run(raw)
+        }
+        None => {
+            interrupt::install();
+            merge::run(args.into()).map(drop)
+        }
+    };
+    if let Err(e) = &result
+        && e.is::<interrupt::Interrupted>()
+    {
+        eprintln!("{}", e);
+        process::exit(130);
+    }
+    // `--stdout | head` has read all it wanted
+    if let Err(e) = &result
+        && e.chain()
+            .filter_map(|cause| cause.downcast_ref::<io::Error>())
+            .any(|cause| cause.kind() == io::ErrorKind::BrokenPipe)
+    {
+        return Ok(());
+    }
+    result
+}
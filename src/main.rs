@@ -1,11 +1,3 @@
-mod cli;
-mod merge;
-
-use anyhow::Result;
-use clap::Parser;
-use cli::Args;
-
-fn main() -> Result<()> {
-    let args = Args::parse();
-    merge::run(args)
+fn main() -> anyhow::Result<()> {
+    treemerge::run_cli(std::env::args_os())
 }
@@ -0,0 +1,179 @@
+use crate::throttle;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// One included file
+#[derive(Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+    pub mtime: Option<String>,
+    /// Permission bits in octal (Unix only), e.g. `0755`
+    pub mode: Option<String>,
+    /// Last commit touching the file, when known
+    pub commit: Option<String>,
+    /// `uid:gid`, recorded with `--xattrs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Hard link count, recorded with `--xattrs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<u64>,
+    /// Extended attributes selected by `--xattrs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xattrs: Option<BTreeMap<String, String>>,
+}
+
+/// Machine-readable record of everything that went into a merge
+#[derive(Serialize)]
+pub struct Manifest {
+    pub tool: &'static str,
+    pub version: &'static str,
+    pub root: String,
+    /// HEAD of the repository containing the root, when there is one
+    pub commit: Option<String>,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// Hex-encoded SHA-256 of a file's contents
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut throttle::reader(File::open(path)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(format!("{:04o}", meta.permissions().mode() & 0o7777))
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &fs::Metadata) -> Option<String> {
+    None
+}
+
+impl ManifestEntry {
+    /// Describe a file on disk
+    pub fn for_file(path: &Path, shown: String, commit: Option<String>) -> Result<ManifestEntry> {
+        let meta = fs::metadata(path)?;
+        let mtime = meta
+            .modified()
+            .ok()
+            .map(|t| DateTime::<Utc>::from(t).to_rfc3339());
+        Ok(ManifestEntry {
+            path: shown,
+            size: meta.len(),
+            sha256: sha256_file(path)?,
+            mtime,
+            mode: file_mode(&meta),
+            commit,
+            owner: None,
+            links: None,
+            xattrs: None,
+        })
+    }
+}
+
+impl Manifest {
+    /// Write the manifest as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut w = BufWriter::new(
+            File::create(path).with_context(|| format!("cannot create {}", path.display()))?,
+        );
+        serde_json::to_writer_pretty(&mut w, self)?;
+        writeln!(w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+/// What a manifest says about one file, as `unmerge --manifest` reads it
+#[derive(Deserialize)]
+pub struct Recorded {
+    pub path: String,
+    pub sha256: String,
+    pub mtime: Option<String>,
+    pub mode: Option<String>,
+}
+
+impl Recorded {
+    /// Give the file at `path` the recorded mtime and permission bits;
+    /// setuid, setgid and sticky bits are not restored
+    pub fn apply(&self, path: &Path) -> Result<()> {
+        if let Some(mtime) = &self.mtime {
+            let time = DateTime::parse_from_rfc3339(mtime).with_context(|| format!("bad mtime {:?}", mtime))?;
+            File::options().write(true).open(path)?.set_modified(SystemTime::from(time))?;
+        }
+        #[cfg(unix)]
+        if let Some(mode) = &self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = u32::from_str_radix(mode, 8).with_context(|| format!("bad mode {:?}", mode))?;
+            fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+        Ok(())
+    }
+}
+
+/// The file entries of a manifest written by `--manifest`
+pub fn read(path: &Path) -> Result<Vec<Recorded>> {
+    #[derive(Deserialize)]
+    struct Files {
+        files: Vec<Recorded>,
+    }
+    let text = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let manifest: Files =
+        serde_json::from_str(&text).with_context(|| format!("{}: not a treemerge manifest", path.display()))?;
+    Ok(manifest.files)
+}
+
+/// Sign `path` with an SSH key (`ssh-keygen -Y sign`), producing `<path>.sig`
+pub fn sign(path: &Path, key: &Path) -> Result<PathBuf> {
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-Y", "sign", "-n", "treemerge", "-f"])
+        .arg(key)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow!("cannot run ssh-keygen: {}", e))?;
+    if !status.success() {
+        return Err(anyhow!("signing {} failed", path.display()));
+    }
+    Ok(PathBuf::from(format!("{}.sig", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_describe_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "abc").unwrap();
+        let entry = ManifestEntry::for_file(&path, "a.txt".into(), Some("c0ffee".into())).unwrap();
+        assert_eq!(entry.path, "a.txt");
+        assert_eq!(entry.size, 3);
+        assert_eq!(entry.sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(entry.commit.as_deref(), Some("c0ffee"));
+        assert!(entry.mtime.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn modes_are_octal_permission_bits() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.sh");
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+        assert_eq!(file_mode(&fs::metadata(&path).unwrap()).as_deref(), Some("0750"));
+    }
+}
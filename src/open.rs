@@ -0,0 +1,100 @@
+use crate::summarize::{shell, shell_quote};
+use anyhow::{Result, anyhow};
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Command opening a file with the desktop's default application
+fn platform_opener(path: &Path) -> Command {
+    if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]).arg(path);
+        c
+    } else {
+        let mut c = Command::new(if cfg!(target_os = "macos") { "open" } else { "xdg-open" });
+        c.arg(path);
+        c
+    }
+}
+
+/// Open the merged outputs for review
+///
+/// HTML goes to the platform opener (a browser, usually); anything else
+/// to `$VISUAL` or `$EDITOR`, waiting for it to exit, and to the platform
+/// opener when neither is set.
+pub fn open(paths: &[PathBuf]) -> Result<()> {
+    let is_html = |p: &PathBuf| {
+        p.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+    };
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|v| env::var(v).ok())
+        .find(|v| !v.trim().is_empty());
+
+    match editor {
+        Some(editor) if !paths.iter().any(is_html) => {
+            // Through the shell, as editors are often set with arguments (`code -w`)
+            let files: Vec<String> = paths.iter().map(|p| shell_quote(&p.to_string_lossy())).collect();
+            let status = shell(&format!("{} {}", editor, files.join(" ")))
+                .status()
+                .map_err(|e| anyhow!("cannot run {}: {}", editor, e))?;
+            if !status.success() {
+                return Err(anyhow!("{} exited with {}", editor, status));
+            }
+        }
+        _ => {
+            for path in paths {
+                platform_opener(path)
+                    .spawn()
+                    .map_err(|e| anyhow!("cannot open {}: {}", path.display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Page through the outputs with `$PAGER` (`less`, or `more` on Windows)
+///
+/// Only when stdout is a terminal; piped runs are left alone.
+pub fn page(paths: &[PathBuf]) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        return Ok(());
+    }
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "more" } else { "less" }.to_string());
+    let files: Vec<String> = paths.iter().map(|p| shell_quote(&p.to_string_lossy())).collect();
+    let status = shell(&format!("{} {}", pager, files.join(" ")))
+        .status()
+        .map_err(|e| anyhow!("cannot run {}: {}", pager, e))?;
+    if !status.success() {
+        return Err(anyhow!("{} exited with {}", pager, status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opener_commands() {
+        let command = platform_opener(Path::new("out.html"));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args.last().copied(), Some(Path::new("out.html").as_os_str()));
+        if cfg!(target_os = "linux") {
+            assert_eq!(command.get_program(), "xdg-open");
+        }
+    }
+
+    #[test]
+    fn no_pager_without_a_terminal() {
+        if !io::stdout().is_terminal() {
+            page(&[PathBuf::from("does/not/exist.txt")]).unwrap();
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::cli::{HeaderStyle, OutputEncoding, OutputFormat};
+use crate::merge::{self, compile_globs};
+use crate::settings::{Diagnostics, Settings};
+use anyhow::{Result, anyhow};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// What to merge and how to lay it out, with the command line's defaults
+///
+/// The user configuration file is not read.
+#[derive(Clone, Debug)]
+pub struct MergeOptions {
+    root: PathBuf,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    extensions: Vec<String>,
+    all_files: bool,
+    gitignore: bool,
+    header_style: HeaderStyle,
+    format: OutputFormat,
+    encoding: OutputEncoding,
+    split_every: Option<usize>,
+    diagnostics: Diagnostics,
+}
+
+impl MergeOptions {
+    /// Merge the tree at `root`
+    pub fn new(root: impl AsRef<Path>) -> MergeOptions {
+        MergeOptions {
+            root: root.as_ref().to_path_buf(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            extensions: Vec::new(),
+            all_files: false,
+            gitignore: true,
+            header_style: HeaderStyle::Hash,
+            format: OutputFormat::Text,
+            encoding: OutputEncoding::Utf8,
+            split_every: None,
+            diagnostics: Diagnostics::default(),
+        }
+    }
+
+    /// Keep files matching this root-relative glob, whatever the excludes say (`--include`)
+    pub fn include(mut self, glob: impl Into<String>) -> MergeOptions {
+        self.include.push(glob.into());
+        self
+    }
+
+    /// Leave out files matching this root-relative glob (`--exclude`)
+    pub fn exclude(mut self, glob: impl Into<String>) -> MergeOptions {
+        self.exclude.push(glob.into());
+        self
+    }
+
+    /// Take files with this extension as text without looking at their
+    /// content, and no others (`--ext`)
+    pub fn extension(mut self, ext: impl Into<String>) -> MergeOptions {
+        self.extensions.push(ext.into());
+        self
+    }
+
+    /// Turn off the built-in excludes (`--all-files`)
+    pub fn all_files(mut self, yes: bool) -> MergeOptions {
+        self.all_files = yes;
+        self
+    }
+
+    /// Honor .gitignore and .ignore files (default: true)
+    pub fn gitignore(mut self, yes: bool) -> MergeOptions {
+        self.gitignore = yes;
+        self
+    }
+
+    pub fn header_style(mut self, style: HeaderStyle) -> MergeOptions {
+        self.header_style = style;
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> MergeOptions {
+        self.format = format;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: OutputEncoding) -> MergeOptions {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Start a new part after this many lines (`--split-every`); only
+    /// [`Merger::merge_to_file`] can split
+    pub fn split_every(mut self, lines: usize) -> MergeOptions {
+        self.split_every = Some(lines);
+        self
+    }
+
+    /// Pass the warnings and notes of a merge to `sink` instead of
+    /// printing them to standard error
+    pub fn diagnostics(mut self, sink: impl Fn(&str) + Send + Sync + 'static) -> MergeOptions {
+        self.diagnostics = Diagnostics::new(sink);
+        self
+    }
+
+    /// Check the options, e.g. that the globs compile
+    pub fn build(self) -> Result<Merger> {
+        compile_globs(&self.include)?;
+        compile_globs(&self.exclude)?;
+        if self.split_every == Some(0) {
+            return Err(anyhow!("split_every must be at least 1"));
+        }
+        Ok(Merger { options: self })
+    }
+}
+
+/// A checked set of [`MergeOptions`], ready to run any number of times
+#[derive(Clone, Debug)]
+pub struct Merger {
+    options: MergeOptions,
+}
+
+impl Merger {
+    /// The merge these options stand for
+    fn settings(&self) -> Settings {
+        let o = &self.options;
+        Settings {
+            path: Some(o.root.clone()),
+            include: o.include.clone(),
+            exclude: o.exclude.clone(),
+            ext: o.extensions.clone(),
+            all_files: o.all_files,
+            no_gitignore: !o.gitignore,
+            header_style: o.header_style,
+            format: o.format,
+            output_encoding: o.encoding,
+            split_every: o.split_every,
+            // Nobody is there to answer
+            no_confirm: true,
+            diagnostics: o.diagnostics.clone(),
+            ..Settings::default()
+        }
+    }
+
+    /// Write the merge to `writer`; returns the number of sections written
+    ///
+    /// The output is the one the command line writes, less its preamble
+    /// and reports. Fails when splitting was asked for.
+    pub fn merge_to_writer<W: Write>(&self, writer: W) -> Result<usize> {
+        if self.options.split_every.is_some() {
+            return Err(anyhow!("split_every needs merge_to_file"));
+        }
+        merge::merge_to_writer(&self.settings(), writer)
+    }
+
+    /// Write the merge to `path` and, when split, its `.partN` files, as the
+    /// command line does; returns the files written
+    pub fn merge_to_file(&self, path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let mut settings = self.settings();
+        settings.output = Some(path.as_ref().to_path_buf());
+        Ok(merge::run(settings)?.outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_to_a_writer_or_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("a.txt"), "a\n").unwrap();
+        std::fs::write(root.join("b.md"), "b\n").unwrap();
+        let merger = MergeOptions::new(&root).exclude("*.md").build().unwrap();
+        let mut out = Vec::new();
+        assert_eq!(merger.merge_to_writer(&mut out).unwrap(), 1);
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("a.txt") && !text.contains("b.md"), "{}", text);
+
+        let split = MergeOptions::new(&root).split_every(1).build().unwrap();
+        assert!(split.merge_to_writer(Vec::new()).is_err());
+        split.merge_to_file(dir.path().join("out.txt")).unwrap();
+        assert!(dir.path().join("out.txt").is_file());
+    }
+
+    #[test]
+    fn split_every_is_checked() {
+        assert!(MergeOptions::new(".").split_every(0).build().is_err());
+        assert!(MergeOptions::new(".").include("[").build().is_err());
+    }
+
+    #[test]
+    fn roots_named_like_subcommands_or_options() {
+        for root in ["why", "batch", "unmerge", "--help", "-x"] {
+            let settings = MergeOptions::new(root).build().unwrap().settings();
+            assert_eq!(settings.path.as_deref(), Some(Path::new(root)));
+        }
+    }
+}
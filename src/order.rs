@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::Path;
+
+/// The paths and globs of an `--order-file`, whose files go first
+pub struct OrderFile {
+    globs: GlobSet,
+}
+
+impl OrderFile {
+    /// One root-relative path or glob per line, `#` starting a comment line
+    pub fn load(path: &Path) -> Result<OrderFile> {
+        let text = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let mut builder = GlobSetBuilder::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let line = line.strip_prefix("./").unwrap_or(line);
+            builder.add(Glob::new(line).with_context(|| format!("invalid glob in {}: {}", path.display(), line))?);
+        }
+        Ok(OrderFile {
+            globs: builder.build()?,
+        })
+    }
+
+    /// The line of the first entry matching `rel`; files not listed come
+    /// after all the others
+    pub fn rank(&self, rel: &str) -> usize {
+        self.globs.matches(rel).into_iter().min().unwrap_or(usize::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_by_first_matching_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("order");
+        fs::write(&file, "# key files\n./README.md\n\nsrc/lib/*\nCargo.toml\nsrc/**\n").unwrap();
+        let order = OrderFile::load(&file).unwrap();
+        assert_eq!(order.rank("README.md"), 0);
+        assert_eq!(order.rank("src/lib/a.rs"), 1);
+        assert_eq!(order.rank("Cargo.toml"), 2);
+        assert_eq!(order.rank("src/main.rs"), 3);
+        assert_eq!(order.rank("docs/a.md"), usize::MAX);
+        fs::write(&file, "src/[\n").unwrap();
+        assert!(OrderFile::load(&file).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+/// Number of leading lines kept when a language has no declaration rules
+const FALLBACK_LINES: usize = 10;
+
+/// Languages understood by `is_declaration`
+const RULE_LANGS: &[&str] = &[
+    "rust", "python", "javascript", "typescript", "vue", "svelte", "go", "ruby", "elixir", "java",
+    "kotlin", "scala", "swift", "csharp", "c", "cpp", "r", "julia", "lua", "bash", "markdown", "rst",
+    "asciidoc",
+];
+
+/// Reduce source text to its declaration lines, prefixed with line numbers
+///
+/// The rules are deliberately shallow (keyword prefixes per language), which
+/// is enough to give a reader the shape of a file without its bodies.
+pub fn outline(lang: Option<&str>, content: &str) -> String {
+    let lang = lang.filter(|l| RULE_LANGS.contains(l));
+    let mut out = String::new();
+    let mut kept = 0;
+
+    for (idx, line) in content.lines().enumerate() {
+        let keep = match lang {
+            Some(lang) => is_declaration(lang, line),
+            None => idx < FALLBACK_LINES,
+        };
+        if keep {
+            out.push_str(&format!("{:>5}: {}\n", idx + 1, line.trim_end()));
+            kept += 1;
+        }
+    }
+
+    let total = content.lines().count();
+    out.push_str(&format!("({} of {} lines shown)\n", kept, total));
+    out
+}
+
+/// Strip modifiers that may precede a declaration keyword
+fn strip_modifiers<'a>(mut s: &'a str, modifiers: &[&str]) -> &'a str {
+    loop {
+        let before = s;
+        for m in modifiers {
+            if let Some(rest) = s.strip_prefix(m)
+                && rest.starts_with(' ')
+            {
+                s = rest.trim_start();
+            }
+        }
+        if s.starts_with("pub(")
+            && let Some(end) = s.find(')')
+        {
+            s = s[end + 1..].trim_start();
+        }
+        if s == before {
+            return s;
+        }
+    }
+}
+
+fn starts_with_any(s: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|k| {
+        s.strip_prefix(k)
+            .is_some_and(|rest| rest.is_empty() || !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+    })
+}
+
+fn is_declaration(lang: &str, line: &str) -> bool {
+    let t = line.trim_start();
+    if t.is_empty() {
+        return false;
+    }
+
+    match lang {
+        "rust" => {
+            let t = strip_modifiers(t, &["pub", "async", "unsafe", "extern \"C\"", "default"]);
+            starts_with_any(
+                t,
+                &["fn", "struct", "enum", "trait", "impl", "mod", "type", "static", "macro_rules!"],
+            ) || t.starts_with("const fn")
+                || (t.starts_with("const ") && !line.starts_with(char::is_whitespace))
+        }
+        "python" => starts_with_any(t, &["def", "class", "async def"]),
+        "javascript" | "typescript" | "vue" | "svelte" => {
+            let t = strip_modifiers(t, &["export", "default", "async", "declare", "abstract"]);
+            starts_with_any(t, &["function", "class", "interface", "type", "enum", "namespace"])
+                || ((t.starts_with("const ") || t.starts_with("let "))
+                    && (t.contains("=>") || t.contains("function"))
+                    && !line.starts_with(char::is_whitespace))
+        }
+        "go" => starts_with_any(t, &["func", "type"]),
+        "ruby" | "elixir" => starts_with_any(t, &["def", "defp", "class", "module", "defmodule"]),
+        "java" | "kotlin" | "scala" | "swift" | "csharp" => {
+            let t = strip_modifiers(
+                t,
+                &[
+                    "public", "private", "protected", "internal", "static", "final", "abstract",
+                    "override", "open", "sealed", "data", "async",
+                ],
+            );
+            starts_with_any(
+                t,
+                &["class", "interface", "enum", "record", "struct", "object", "trait", "fun", "def", "func"],
+            ) || (t != line.trim_start() && t.contains('(') && !t.ends_with(';'))
+        }
+        "c" | "cpp" => {
+            starts_with_any(t, &["struct", "class", "typedef", "enum", "namespace", "template"])
+                || (!line.starts_with(char::is_whitespace)
+                    && t.contains('(')
+                    && !t.ends_with(';')
+                    && !t.starts_with(['#', '/', '*', '{', '}']))
+        }
+        "r" => t.contains("<- function") || t.contains("= function"),
+        "julia" => starts_with_any(t, &["function", "struct", "mutable struct", "module", "macro"]),
+        "lua" => starts_with_any(t, &["function", "local function"]),
+        "bash" => t.starts_with("function ") || (t.contains("()") && t.ends_with('{')),
+        "markdown" => t.starts_with('#'),
+        "rst" | "asciidoc" => t.starts_with('=') && line.starts_with('='),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outlines_keep_declarations() {
+        let src = "use std::fs;\n\npub fn run() {\n    let x = 1;\n}\n\nstruct A;\n";
+        assert_eq!(outline(Some("rust"), src), "    3: pub fn run() {\n    7: struct A;\n(2 of 7 lines shown)\n");
+
+        let py = "import os\nclass A:\n    def f(self):\n        pass\n";
+        assert_eq!(outline(Some("python"), py), "    2: class A:\n    3:     def f(self):\n(2 of 4 lines shown)\n");
+    }
+
+    #[test]
+    fn unknown_languages_keep_the_first_lines() {
+        let src: String = (1..=12).map(|n| format!("{n}\n")).collect();
+        let out = outline(None, &src);
+        assert!(out.starts_with("    1: 1\n"));
+        assert!(out.ends_with("   10: 10\n(10 of 12 lines shown)\n"));
+    }
+}
@@ -0,0 +1,102 @@
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b").unwrap()
+});
+// Full form, or compressed with `::` between hex groups
+static IPV6: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\b(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}\b",
+        r"|\b(?:[0-9A-Fa-f]{1,4}:){1,6}:(?:[0-9A-Fa-f]{1,4}:){0,5}[0-9A-Fa-f]{1,4}\b",
+    ))
+    .unwrap()
+});
+static IPV4: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b").unwrap()
+});
+// International prefix or area code, then two or three groups of digits
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)[ .-]?|\b\d{2,4}[ .-])\d{3,4}[ .-]\d{3,4}\b").unwrap()
+});
+
+/// Whether a match, with the text before and after it, is worth masking
+type Plausible = fn(&str, &str, &str) -> bool;
+
+fn always(_: &str, _: &str, _: &str) -> bool {
+    true
+}
+
+// Shorter runs are more likely counts, sizes or dates
+fn phone_like(_: &str, found: &str, _: &str) -> bool {
+    let digits = found.chars().filter(char::is_ascii_digit).count();
+    (9..=15).contains(&digits)
+}
+
+// Paths such as `Add::add` or `Face::add(x)` are all hex letters too: an
+// address has a digit somewhere, is written in one case and is not part
+// of a path or a call
+fn ipv6_like(before: &str, found: &str, after: &str) -> bool {
+    let mixed_case = found.contains(|c: char| c.is_ascii_uppercase()) && found.contains(|c: char| c.is_ascii_lowercase());
+    found.contains(|c: char| c.is_ascii_digit())
+        && !mixed_case
+        && !before.ends_with("::")
+        && !before.ends_with('.')
+        && !after.starts_with("::")
+        && !after.starts_with(['(', '<', '!'])
+}
+
+/// Mask emails, IP addresses and phone numbers in `text`
+///
+/// Returns the redacted text and the number of replacements.
+pub fn redact(text: &str) -> (String, usize) {
+    let mut count = 0;
+    let mut text = text.to_string();
+    let patterns: [(&Regex, &str, Plausible); 4] = [
+        (&EMAIL, "[email redacted]", always),
+        (&IPV6, "[ip redacted]", ipv6_like),
+        (&IPV4, "[ip redacted]", always),
+        (&PHONE, "[phone redacted]", phone_like),
+    ];
+    for (regex, mask, plausible) in patterns {
+        text = regex
+            .replace_all(&text, |caps: &Captures| {
+                let found = caps.get(0).unwrap();
+                if !plausible(&text[..found.start()], found.as_str(), &text[found.end()..]) {
+                    return found.as_str().to_string();
+                }
+                count += 1;
+                mask.to_string()
+            })
+            .into_owned();
+    }
+    (text, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_and_numbers() {
+        let (text, count) = redact("mail jo@example.org from 10.0.0.1 or fe80::1ff:fe23:4567:890a, +44 20 7946 0958");
+        assert_eq!(text, "mail [email redacted] from [ip redacted] or [ip redacted], [phone redacted]");
+        assert_eq!(count, 4);
+        let (text, _) = redact("ping 2001:db8::8a2e:370:7334 and 2001:0db8:0000:0000:0000:ff00:0042:8329");
+        assert_eq!(text, "ping [ip redacted] and [ip redacted]");
+    }
+
+    #[test]
+    fn rust_paths_are_not_addresses() {
+        for code in [
+            "impl Add for V { fn add(self) -> V { Add::add(a, b) } }",
+            "let f = Face::add(x);",
+            "use std::ops::Add::add;",
+            "let x = C0::new();",
+            "match e { Bad::Cafe => 1, Ab12::Cd => 2 }",
+            "let n = 2021; let v = vec![1, 2, 3];",
+        ] {
+            assert_eq!(redact(code), (code.to_string(), 0), "{}", code);
+        }
+    }
+}
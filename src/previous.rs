@@ -0,0 +1,307 @@
+use crate::cli::{HeaderStyle, OutputFormat};
+use crate::encoding;
+use crate::merge::{MARKER, SAFE_DELIMITERS, part_name, parts_index};
+use crate::structured;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// One file section read back from an output
+pub struct Entry {
+    /// As shown in the header
+    pub path: String,
+    /// The bracketed notes after the path, in order
+    pub notes: Vec<String>,
+    pub body: String,
+}
+
+impl Entry {
+    /// Split a header into the path and the `  [note]` suffixes the writer appends
+    fn new(mut header: &str, body: String) -> Entry {
+        let mut notes = Vec::new();
+        while header.ends_with(']')
+            && let Some(i) = header.rfind("  [")
+        {
+            notes.insert(0, header[i + 3..header.len() - 1].to_string());
+            header = &header[..i];
+        }
+        Entry {
+            path: header.to_string(),
+            notes,
+            body,
+        }
+    }
+}
+
+/// The header at `lines[i]`, if one starts there, and the number of lines it
+/// takes up to and including the blank line after it
+fn header(lines: &[&str], i: usize, style: HeaderStyle) -> Option<(String, usize)> {
+    let blank = |j: usize| lines.get(j).is_some_and(|l| *l == "\n");
+    let line = lines[i].trim_end_matches('\n');
+    match style {
+        HeaderStyle::Plain => line.strip_prefix(">>> ").filter(|_| blank(i + 1)).map(|h| (h.into(), 2)),
+        HeaderStyle::Hash => line.strip_prefix("########## ").filter(|_| blank(i + 1)).map(|h| (h.into(), 2)),
+        HeaderStyle::Underline => {
+            let rule = lines.get(i + 1)?.trim_end_matches('\n');
+            let underlined = !line.is_empty()
+                && rule.chars().count() == line.chars().count()
+                && rule.chars().all(|c| c == '=');
+            (underlined && blank(i + 2)).then(|| (line.into(), 3))
+        }
+    }
+}
+
+/// Whether a body line would read as (part of) a header in `style`, or is
+/// such a line escaped already; `--delimiters safe` puts a `\` before these
+pub fn needs_escape(line: &str, style: HeaderStyle) -> bool {
+    let line = line.trim_start_matches('\\').trim_end_matches(['\n', '\r']);
+    match style {
+        HeaderStyle::Plain => line.starts_with(">>> "),
+        HeaderStyle::Hash => line.starts_with("########## "),
+        HeaderStyle::Underline => !line.is_empty() && line.chars().all(|c| c == '='),
+    }
+}
+
+/// Whether `text` was written with `--delimiters safe`, going by its marker line
+fn escaped(text: &str) -> bool {
+    text.lines()
+        .next()
+        .and_then(|line| line.strip_prefix(MARKER))
+        .is_some_and(|rest| rest.split_whitespace().any(|w| w == SAFE_DELIMITERS))
+}
+
+/// Files of a plain-text output
+fn parse_text(text: &str, style: HeaderStyle) -> Vec<Entry> {
+    let unescape = escaped(text);
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut files: Vec<Entry> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((header, len)) = header(&lines, i, style) {
+            // The writer put a newline before the header
+            if let Some(entry) = files.last_mut() {
+                entry.body.pop();
+            }
+            files.push(Entry::new(&header, String::new()));
+            i += len;
+            continue;
+        }
+        if let Some(entry) = files.last_mut() {
+            let line = lines[i];
+            match line.strip_prefix('\\') {
+                Some(rest) if unescape && needs_escape(rest, style) => entry.body.push_str(rest),
+                _ => entry.body.push_str(line),
+            }
+        }
+        i += 1;
+    }
+    files
+}
+
+/// Files of a markdown output: `## path` headings followed by a fence;
+/// headings without one (instructions) are passed over
+///
+/// Body lines keep their endings. The writer ends a file without a final
+/// newline with one before the closing fence, so such files come back with one.
+fn parse_markdown(text: &str) -> Vec<Entry> {
+    let mut files = Vec::new();
+    // The writer's own lines end in `\n`; the bodies' lines as they were
+    let mut lines = text.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        let Some(heading) = line.strip_suffix('\n').and_then(|l| l.strip_prefix("## ")) else {
+            continue;
+        };
+        if lines.next_if(|l| *l == "\n").is_none() {
+            continue;
+        }
+        let Some(open) = lines.next_if(|l| l.starts_with("```")) else {
+            continue;
+        };
+        let fence = format!("{}\n", open.chars().take_while(|&c| c == '`').collect::<String>());
+        let mut body = String::new();
+        for line in lines.by_ref() {
+            if line == fence {
+                break;
+            }
+            body.push_str(line);
+        }
+        files.push(Entry::new(heading, body));
+    }
+    files
+}
+
+/// A file record of a JSON or JSON Lines output
+#[derive(Deserialize)]
+struct Record {
+    path: String,
+    #[serde(default)]
+    notes: Vec<String>,
+    content: String,
+}
+
+/// Files of a `--format json` output; none when it is not one
+fn parse_json(text: &str) -> Vec<Entry> {
+    #[derive(Deserialize)]
+    struct Document {
+        files: Vec<Record>,
+    }
+    if !text.starts_with(structured::JSON_START) {
+        return Vec::new();
+    }
+    serde_json::from_str::<Document>(text)
+        .map(|d| d.files.into_iter().map(Entry::from).collect())
+        .unwrap_or_default()
+}
+
+/// Files of a `--format jsonl` output: its `file` records
+fn parse_jsonl(text: &str) -> Vec<Entry> {
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    enum Line {
+        Manifest,
+        File(Record),
+    }
+    if !text.starts_with(structured::JSONL_START) {
+        return Vec::new();
+    }
+    text.lines()
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(Line::File(record)) => Some(Entry::from(record)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl From<Record> for Entry {
+    fn from(record: Record) -> Entry {
+        Entry {
+            path: record.path,
+            notes: record.notes,
+            body: record.content,
+        }
+    }
+}
+
+/// The files of an output written with these layout options
+pub fn parse(text: &str, format: OutputFormat, style: HeaderStyle) -> Vec<Entry> {
+    match format {
+        OutputFormat::Text => parse_text(text, style),
+        OutputFormat::Markdown => parse_markdown(text),
+        OutputFormat::Json => parse_json(text),
+        OutputFormat::Jsonl => parse_jsonl(text),
+    }
+}
+
+/// The layout `text` was most likely written with: the one finding the
+/// most files, markdown and then the default header style winning ties
+pub fn detect(text: &str) -> (OutputFormat, HeaderStyle) {
+    [
+        (OutputFormat::Markdown, HeaderStyle::Hash),
+        (OutputFormat::Text, HeaderStyle::Hash),
+        (OutputFormat::Text, HeaderStyle::Plain),
+        (OutputFormat::Text, HeaderStyle::Underline),
+        (OutputFormat::Json, HeaderStyle::Hash),
+        (OutputFormat::Jsonl, HeaderStyle::Hash),
+    ]
+    .into_iter()
+    .rev()
+    .max_by_key(|&(format, style)| parse(text, format, style).len())
+    .unwrap_or((OutputFormat::Text, HeaderStyle::Hash))
+}
+
+/// Text of the output at `base` and of its split parts, in order, or None
+/// when there is no such output
+pub fn read(base: &Path) -> Result<Option<Vec<String>>> {
+    let mut paths = vec![base.to_path_buf()];
+    if let Ok(names) = fs::read_to_string(parts_index(base)) {
+        // --stable-ids parts, in order; the first is the base itself
+        paths = names
+            .lines()
+            .filter(|n| !n.is_empty())
+            .map(|n| base.with_file_name(n))
+            .collect();
+    } else {
+        paths.extend((1..).map(|i| part_name(base, i)).take_while(|p| p.is_file()));
+    }
+    let mut texts = Vec::new();
+    for path in paths.iter().filter(|p| p.is_file()) {
+        let bytes = fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+        texts.push(encoding::decode(&bytes));
+    }
+    Ok((!texts.is_empty()).then_some(texts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostile_inputs_find_nothing() {
+        for text in ["", "\n\n", "########## a.rs", "########## a.rs\nno blank line\n", "{\"files\": 3}", "{not json", "## a\n\n```\nunclosed\n"] {
+            for format in [OutputFormat::Text, OutputFormat::Json, OutputFormat::Jsonl] {
+                assert!(parse(text, format, HeaderStyle::Hash).is_empty(), "{:?} as {:?}", text, format);
+            }
+        }
+        let lines = format!(
+            "{}\"version\":\"0.0.5\"}}\n{{\"type\":\"file\",\"path\":\"a\",\"content\":\"\"}}\nnot json\n",
+            structured::JSONL_START
+        );
+        assert_eq!(parse(&lines, OutputFormat::Jsonl, HeaderStyle::Hash).len(), 1);
+    }
+
+    #[test]
+    fn underline_headers_and_detection() {
+        let text = "@treemerge-output 0.0.5\n\nsrc/ü.rs\n========\n\nlet x = 1;\n==\n";
+        let files = parse(text, OutputFormat::Text, HeaderStyle::Underline);
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].path.as_str(), files[0].body.as_str()), ("src/ü.rs", "let x = 1;\n==\n"));
+        assert_eq!(detect(text), (OutputFormat::Text, HeaderStyle::Underline));
+        assert_eq!(detect("## a.md\n\n```\nx\n```\n"), (OutputFormat::Markdown, HeaderStyle::Hash));
+        assert_eq!(detect("nothing here\n"), (OutputFormat::Markdown, HeaderStyle::Hash));
+    }
+
+    #[test]
+    fn text_headers_notes_and_escapes() {
+        let text = "@treemerge-output 0.0.5 delimiters=safe\n\n########## a.rs  [lines 3-7 of 50]  [truncated]\n\nfn a() {}\n\\########## b.rs\n\n########## b.rs\n\nno newline";
+        let files = parse(text, OutputFormat::Text, HeaderStyle::Hash);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "a.rs");
+        assert_eq!(files[0].notes, ["lines 3-7 of 50", "truncated"]);
+        assert_eq!(files[0].body, "fn a() {}\n########## b.rs\n");
+        assert_eq!((files[1].path.as_str(), files[1].body.as_str()), ("b.rs", "no newline"));
+        // Without the marker, backslashes are the file's own
+        let plain = text.replacen(" delimiters=safe", "", 1);
+        assert_eq!(parse(&plain, OutputFormat::Text, HeaderStyle::Hash)[0].body, "fn a() {}\n\\########## b.rs\n");
+    }
+
+    #[test]
+    fn lines_needing_escapes() {
+        assert!(needs_escape("########## x\n", HeaderStyle::Hash));
+        assert!(needs_escape("\\\\########## x", HeaderStyle::Hash));
+        assert!(!needs_escape(" ########## x", HeaderStyle::Hash));
+        assert!(!needs_escape("##########x", HeaderStyle::Hash));
+        assert!(needs_escape(">>> x\r\n", HeaderStyle::Plain));
+        assert!(!needs_escape(">>> x", HeaderStyle::Hash));
+        assert!(needs_escape("===\n", HeaderStyle::Underline));
+        assert!(!needs_escape("\n", HeaderStyle::Underline));
+        assert!(!needs_escape("== x", HeaderStyle::Underline));
+    }
+
+    #[test]
+    fn json_records() {
+        let json = format!(r#"{} "version": "0.0.5"}}, "files": [{{"path": "a", "content": "x\n"}}]}}"#, structured::JSON_START);
+        assert_eq!(detect(&json).0, OutputFormat::Json);
+        assert_eq!(parse(&json, OutputFormat::Json, HeaderStyle::Hash)[0].body, "x\n");
+    }
+
+    #[test]
+    fn markdown_keeps_line_endings() {
+        let text = "@treemerge-output 0.0.5\n\n## a.txt\n\n```text\na\r\nb\r\n```\n\n## b.txt  [truncated]\n\n````\n```\n````\n";
+        let files = parse(text, OutputFormat::Markdown, HeaderStyle::Hash);
+        assert_eq!(files.len(), 2);
+        assert_eq!((files[0].path.as_str(), files[0].body.as_str()), ("a.txt", "a\r\nb\r\n"));
+        assert_eq!((files[1].path.as_str(), files[1].notes.as_slice()), ("b.txt", &["truncated".to_string()][..]));
+        assert_eq!(files[1].body, "```\n");
+    }
+}
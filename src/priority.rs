@@ -0,0 +1,141 @@
+/// How much a file matters to a reader getting to know the tree
+///
+/// Higher is more important. The score is a rough heuristic on the
+/// root-relative path alone: READMEs, manifests and entry points first,
+/// then sources, then tests and examples, with vendored or generated code
+/// last; deeper files lose a little.
+pub fn score(rel: &str) -> i32 {
+    let lower = rel.to_ascii_lowercase();
+    let (dirs, name) = lower.rsplit_once('/').unwrap_or(("", &lower));
+    let dirs: Vec<&str> = dirs.split('/').filter(|d| !d.is_empty()).collect();
+    let stem = name.split('.').next().unwrap_or(name);
+    let has_dir = |names: &[&str]| dirs.iter().any(|d| names.contains(d));
+
+    let mut score = 0;
+    if stem == "readme" {
+        score += 50;
+    }
+    if matches!(
+        name,
+        "cargo.toml"
+            | "package.json"
+            | "pyproject.toml"
+            | "setup.py"
+            | "go.mod"
+            | "pom.xml"
+            | "build.gradle"
+            | "build.gradle.kts"
+            | "gemfile"
+            | "composer.json"
+            | "description"
+            | "cmakelists.txt"
+            | "makefile"
+    ) {
+        score += 40;
+    }
+    if matches!(stem, "main" | "lib" | "index" | "app" | "mod" | "__init__" | "__main__") {
+        score += 30;
+    }
+    if has_dir(&["src", "lib", "app", "pkg", "cmd", "internal", "r"]) {
+        score += 10;
+    }
+    if has_dir(&["doc", "docs"]) || name.ends_with(".md") {
+        score += 5;
+    }
+    if has_dir(&["test", "tests", "spec", "__tests__"])
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+    {
+        score -= 10;
+    }
+    if has_dir(&["example", "examples", "bench", "benches", "fixtures", "testdata"]) {
+        score -= 20;
+    }
+    if has_dir(&["vendor", "third_party", "generated", "gen"])
+        || name.contains(".min.")
+        || name.ends_with(".pb.go")
+        || name.contains("_pb2")
+    {
+        score -= 40;
+    }
+    score - 2 * dirs.len() as i32
+}
+
+/// Whether a file named `name` is a conventional place to start reading
+///
+/// Program entry points (`main.rs`, `index.ts`, `__main__.py`, ...) and
+/// the build manifests that define them (`Cargo.toml`, `package.json`,
+/// `pom.xml`, ...).
+pub fn is_entry_point(name: &str) -> bool {
+    if name.ends_with(".csproj") || name.ends_with(".cabal") {
+        return true;
+    }
+    if matches!(
+        name,
+        "Cargo.toml"
+            | "package.json"
+            | "pyproject.toml"
+            | "setup.py"
+            | "go.mod"
+            | "pom.xml"
+            | "build.gradle"
+            | "build.gradle.kts"
+            | "CMakeLists.txt"
+            | "Makefile"
+            | "Gemfile"
+            | "config.ru"
+            | "mix.exs"
+            | "DESCRIPTION"
+            | "Program.cs"
+            | "Main.java"
+            | "Application.java"
+    ) {
+        return true;
+    }
+    let Some((stem, ext)) = name.rsplit_once('.') else {
+        return false;
+    };
+    match ext {
+        "rs" => matches!(stem, "main" | "lib"),
+        "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" => {
+            matches!(stem, "index" | "main" | "app" | "server")
+        }
+        "py" => matches!(stem, "__main__" | "main" | "app" | "manage" | "wsgi" | "asgi"),
+        "go" | "c" | "cc" | "cpp" | "swift" | "kt" => stem == "main",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores() {
+        let mut paths = [
+            "vendor/lib/x.min.js",
+            "examples/demo.rs",
+            "tests/merge_test.rs",
+            "src/util.rs",
+            "src/main.rs",
+            "Cargo.toml",
+            "README.md",
+        ];
+        paths.sort_by_key(|p| std::cmp::Reverse(score(p)));
+        assert_eq!(
+            paths,
+            ["README.md", "Cargo.toml", "src/main.rs", "src/util.rs", "tests/merge_test.rs", "examples/demo.rs", "vendor/lib/x.min.js"]
+        );
+        assert!(score("src/a.rs") > score("src/deep/er/a.rs"));
+    }
+
+    #[test]
+    fn entry_points() {
+        for name in ["main.rs", "lib.rs", "index.ts", "__main__.py", "main.go", "Cargo.toml", "app.csproj"] {
+            assert!(is_entry_point(name), "{}", name);
+        }
+        for name in ["mod.rs", "index.html", "util.py", "cargo.toml", "main"] {
+            assert!(!is_entry_point(name), "{}", name);
+        }
+    }
+}
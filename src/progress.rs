@@ -0,0 +1,43 @@
+use crate::cli::ProgressFormat;
+use crate::settings::Diagnostics;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::{Map, Value};
+
+/// Report one `--progress-format json` event as a JSON line: `fields` (an
+/// object) plus `"event": name`
+pub fn emit(format: ProgressFormat, name: &str, fields: Value, diagnostics: &Diagnostics) {
+    if format != ProgressFormat::Json {
+        return;
+    }
+    let mut event = Map::new();
+    event.insert("event".into(), name.into());
+    if let Value::Object(fields) = fields {
+        event.extend(fields);
+    }
+    diagnostics.emit(Value::Object(event));
+}
+
+/// The bar shown while writing, hidden unless `format` asks for it
+pub fn bar(format: ProgressFormat, len: u64) -> ProgressBar {
+    if format != ProgressFormat::Bar {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new(len);
+    pb.set_style(
+        ProgressStyle::with_template("[{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    pb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bars_only_for_bar_progress() {
+        assert!(bar(ProgressFormat::Json, 3).is_hidden());
+        assert_eq!(bar(ProgressFormat::Bar, 3).length(), Some(3));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::config::config_dir;
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+
+/// Prompt scaffolds shipped with the binary
+const BUILTIN: &[(&str, &str)] = &[
+    ("code-review", include_str!("prompts/code-review.j2")),
+    ("onboarding", include_str!("prompts/onboarding.j2")),
+    ("bug-hunt", include_str!("prompts/bug-hunt.j2")),
+];
+
+/// Template name and source for the prompt `name`
+///
+/// `<config dir>/prompts/<name>.j2` takes precedence over the built-in
+/// prompt of the same name, and may also define new ones.
+pub fn load(name: &str) -> Result<(String, String)> {
+    if let Some(dir) = config_dir() {
+        let path = dir.join("prompts").join(format!("{}.j2", name));
+        if path.is_file() {
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("cannot read prompt {}", path.display()))?;
+            return Ok((path.to_string_lossy().into_owned(), source));
+        }
+    }
+    BUILTIN
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(n, source)| (format!("prompt {}", n), source.to_string()))
+        .ok_or_else(|| {
+            let known: Vec<&str> = BUILTIN.iter().map(|(n, _)| *n).collect();
+            anyhow!("unknown prompt '{}' (built-in: {})", name, known.join(", "))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_prompts_list_the_built_ins() {
+        let err = load("no-such-prompt").unwrap_err().to_string();
+        assert!(err.contains("code-review, onboarding, bug-hunt"), "{}", err);
+    }
+}
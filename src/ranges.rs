@@ -0,0 +1,157 @@
+use anyhow::{Context, Result, anyhow};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::Path;
+
+/// Lines `start` to `end` of a file, counted from 1; no `end` runs to the
+/// last line
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct LineRange {
+    start: usize,
+    end: Option<usize>,
+}
+
+/// Split an include spec `GLOB:START-END` (or `GLOB:START-`) into its glob
+/// and range; any other spec is a glob on its own
+pub fn split_spec(spec: &str) -> Result<(&str, Option<LineRange>)> {
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let Some((glob, range)) = spec.rsplit_once(':') else {
+        return Ok((spec, None));
+    };
+    let Some((start, end)) = range.split_once('-') else {
+        return Ok((spec, None));
+    };
+    if glob.is_empty() || start.is_empty() || !digits(start) || !digits(end) {
+        return Ok((spec, None));
+    }
+    let start: usize = start.parse().with_context(|| format!("invalid line range in {}", spec))?;
+    let end: Option<usize> = if end.is_empty() {
+        None
+    } else {
+        Some(end.parse().with_context(|| format!("invalid line range in {}", spec))?)
+    };
+    if start == 0 || end.is_some_and(|end| end < start) {
+        return Err(anyhow!("invalid line range in {}: lines count from 1, START to END", spec));
+    }
+    Ok((glob, Some(LineRange { start, end })))
+}
+
+/// The specs of a `--ranges-from` file: one per line, `#` starting a comment line
+pub fn load(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// The ranged include specs, matched against root-relative paths
+pub struct Ranges {
+    globs: GlobSet,
+    ranges: Vec<LineRange>,
+}
+
+impl Ranges {
+    /// The specs among `specs` that have a range; the others are left out
+    pub fn new(specs: &[String]) -> Result<Ranges> {
+        let mut builder = GlobSetBuilder::new();
+        let mut ranges = Vec::new();
+        for spec in specs {
+            if let (glob, Some(range)) = split_spec(spec)? {
+                builder.add(Glob::new(glob).context("Invalid glob pattern")?);
+                ranges.push(range);
+            }
+        }
+        Ok(Ranges {
+            globs: builder.build()?,
+            ranges,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The ranges asked for `rel`, in order
+    pub fn of(&self, rel: &str) -> Vec<LineRange> {
+        let mut found: Vec<LineRange> = self.globs.matches(rel).into_iter().map(|i| self.ranges[i]).collect();
+        found.sort();
+        found
+    }
+}
+
+/// The lines of `text` in `ranges` (sorted), each line taken once, and the
+/// header note naming them, e.g. `lines 100-400 of 1200`
+pub fn slice(text: &str, ranges: &[LineRange]) -> (String, String) {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let total = lines.len();
+    // Overlapping ranges run together; ranges past the end are dropped
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for range in ranges {
+        let end = range.end.unwrap_or(total).min(total);
+        if range.start > end {
+            continue;
+        }
+        match spans.last_mut() {
+            Some(last) if range.start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => spans.push((range.start, end)),
+        }
+    }
+    let mut body = String::new();
+    for &(start, end) in &spans {
+        body.extend(lines[start - 1..end].iter().copied());
+    }
+    if !body.is_empty() && !body.ends_with('\n') {
+        body.push('\n');
+    }
+    let shown: Vec<String> = spans.iter().map(|(start, end)| format!("{}-{}", start, end)).collect();
+    let note = if shown.is_empty() {
+        format!("none of its {} lines in range", total)
+    } else {
+        format!("lines {} of {}", shown.join(", "), total)
+    };
+    (body, note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(spec: &str) -> Option<LineRange> {
+        split_spec(spec).unwrap().1
+    }
+
+    #[test]
+    fn specs_with_and_without_ranges() {
+        assert_eq!(split_spec("src/big.rs:100-400").unwrap(), ("src/big.rs", Some(LineRange { start: 100, end: Some(400) })));
+        assert_eq!(range("src/*.rs:5-"), Some(LineRange { start: 5, end: None }));
+        // Globs that happen to hold a colon or a dash
+        for spec in ["src/**", "C:-x", "a:b-c", ":1-2", "a:-3", "a:1", "a:1-2-3"] {
+            assert_eq!(split_spec(spec).unwrap(), (spec, None), "{}", spec);
+        }
+        for spec in ["a.rs:0-3", "a.rs:9-3", "a.rs:99999999999999999999999-"] {
+            assert!(split_spec(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn slices_merge_overlaps_and_stop_at_the_end() {
+        let text = "1\n2\n3\n4\n5\n6";
+        let ranges = [range("a:1-2").unwrap(), range("a:2-3").unwrap(), range("a:5-").unwrap()];
+        assert_eq!(slice(text, &ranges), ("1\n2\n3\n5\n6\n".into(), "lines 1-3, 5-6 of 6".into()));
+        assert_eq!(slice(text, &[range("a:4-100").unwrap()]).1, "lines 4-6 of 6");
+        assert_eq!(slice(text, &[range("a:7-9").unwrap()]), (String::new(), "none of its 6 lines in range".into()));
+        assert_eq!(slice("", &[range("a:1-").unwrap()]).1, "none of its 0 lines in range");
+    }
+
+    #[test]
+    fn ranges_by_path() {
+        let specs = ["src/*.rs:10-20", "src/main.rs:1-5", "README.md"].map(String::from);
+        let ranges = Ranges::new(&specs).unwrap();
+        assert_eq!(ranges.of("src/main.rs"), [range("a:1-5").unwrap(), range("a:10-20").unwrap()]);
+        assert!(ranges.of("README.md").is_empty());
+        assert!(Ranges::new(&["[:1-2".to_string()]).is_err());
+    }
+}
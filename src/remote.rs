@@ -0,0 +1,201 @@
+use crate::git;
+use crate::settings::Diagnostics;
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// A remote repository cloned into a temporary directory
+///
+/// The clone is removed when this is dropped.
+pub struct Checkout {
+    _dir: TempDir,
+    path: PathBuf,
+}
+
+impl Checkout {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Recognize a remote input, returning the URL to clone
+///
+/// Accepts `github:owner/repo`, `http(s)://`, `ssh://`, `git://` and
+/// `file://` URLs, and scp-style `user@host:path`. Existing local
+/// directories always win.
+pub fn parse(input: &Path) -> Option<String> {
+    if input.is_dir() {
+        return None;
+    }
+    let s = input.to_str()?;
+    if let Some(repo) = s.strip_prefix("github:") {
+        let repo = repo.trim_matches('/').trim_end_matches(".git");
+        return (repo.split('/').count() == 2).then(|| format!("https://github.com/{}.git", repo));
+    }
+    let is_url = ["https://", "http://", "ssh://", "git://", "file://"]
+        .iter()
+        .any(|p| s.starts_with(p));
+    let is_scp = s
+        .split_once(':')
+        .is_some_and(|(host, _)| host.contains('@') && !host.contains('/'));
+    (is_url || is_scp).then(|| s.to_owned())
+}
+
+/// Repository name from a URL, used for the default output name
+pub fn repo_name(url: &str) -> &str {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or(url);
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    if name.is_empty() { "treemerge" } else { name }
+}
+
+fn git(args: &[&str], dir: Option<&Path>) -> Result<()> {
+    let mut cmd = Command::new("git");
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Check out the submodules of the repository at `dir` that are missing,
+/// shallow and recursively; checked-out ones are left as they are
+pub fn fetch_submodules(dir: &Path, verbose: bool, diagnostics: &Diagnostics) -> Result<()> {
+    let missing: Vec<String> = git::submodules(dir)
+        .into_iter()
+        .filter(|(_, checked_out)| !checked_out)
+        .map(|(path, _)| path)
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if verbose {
+        diagnostics.emit(format_args!("fetching submodules: {}", missing.join(", ")));
+    }
+    let mut update = vec!["submodule", "update", "--init", "--recursive", "--depth", "1", "--quiet", "--"];
+    update.extend(missing.iter().map(String::as_str));
+    git(&update, Some(dir))
+}
+
+/// Clones kept for later merges of the same process, by URL, depth and
+/// paths, once `share_clones` turned it on
+static CLONES: Mutex<Option<HashMap<String, Arc<Checkout>>>> = Mutex::new(None);
+
+/// Keep clones for later merges of the same repository until `drop_clones`
+pub fn share_clones() {
+    if let Ok(mut clones) = CLONES.lock() {
+        clones.get_or_insert_with(HashMap::new);
+    }
+}
+
+/// Remove the kept clones
+pub fn drop_clones() {
+    if let Ok(mut clones) = CLONES.lock() {
+        *clones = None;
+    }
+}
+
+/// `fetch`, reusing a kept clone of the same URL, depth and paths
+pub fn fetch_shared(
+    url: &str,
+    depth: Option<u32>,
+    paths: &[String],
+    verbose: bool,
+    diagnostics: &Diagnostics,
+) -> Result<Arc<Checkout>> {
+    let key = format!("{} {:?} {:?}", url, depth, paths);
+    if let Some(checkout) = CLONES.lock().ok().and_then(|c| c.as_ref()?.get(&key).cloned()) {
+        if verbose {
+            diagnostics.emit(format_args!("reusing the clone of {}", url));
+        }
+        return Ok(checkout);
+    }
+    let checkout = Arc::new(fetch(url, depth, paths, verbose, diagnostics)?);
+    if let Ok(mut clones) = CLONES.lock()
+        && let Some(clones) = clones.as_mut()
+    {
+        clones.insert(key, checkout.clone());
+    }
+    Ok(checkout)
+}
+
+/// Clone `url`, optionally shallow (`depth`) and restricted to `paths`
+///
+/// With paths, only their blobs are downloaded (partial clone plus a
+/// sparse checkout anchored at the repository root).
+fn fetch(url: &str, depth: Option<u32>, paths: &[String], verbose: bool, diagnostics: &Diagnostics) -> Result<Checkout> {
+    let dir = tempfile::Builder::new()
+        .prefix("treemerge-")
+        .tempdir()
+        .context("cannot create a temporary directory")?;
+    let path = dir.path().join(repo_name(url));
+    let target = path.to_string_lossy().into_owned();
+
+    let depth = depth.map(|d| d.to_string());
+    let mut clone = vec!["clone", "--quiet"];
+    if let Some(depth) = &depth {
+        clone.extend(["--depth", depth]);
+    }
+    if !paths.is_empty() {
+        clone.extend(["--filter=blob:none", "--no-checkout"]);
+    }
+    clone.extend(["--", url, &target]);
+
+    if verbose {
+        diagnostics.emit(format_args!("cloning {}", url));
+    }
+    git(&clone, None)?;
+
+    if !paths.is_empty() {
+        let patterns: Vec<String> = paths
+            .iter()
+            .map(|p| format!("/{}", p.trim_start_matches("./").trim_start_matches('/')))
+            .collect();
+        let mut sparse = vec!["sparse-checkout", "set", "--no-cone", "--"];
+        sparse.extend(patterns.iter().map(String::as_str));
+        git(&sparse, Some(&path))?;
+        git(&["checkout", "--quiet"], Some(&path))?;
+    }
+
+    Ok(Checkout { _dir: dir, path })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_inputs() {
+        assert_eq!(parse(Path::new("github:owner/repo.git")).as_deref(), Some("https://github.com/owner/repo.git"));
+        assert_eq!(parse(Path::new("github:owner")), None);
+        assert_eq!(parse(Path::new("git@example.org:team/app.git")).as_deref(), Some("git@example.org:team/app.git"));
+        assert_eq!(parse(Path::new("ssh://example.org/app")).as_deref(), Some("ssh://example.org/app"));
+        assert_eq!(parse(Path::new("some/dir:with/colon")), None);
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(parse(dir.path()), None);
+    }
+
+    #[test]
+    fn repository_names() {
+        assert_eq!(repo_name("https://github.com/owner/repo.git"), "repo");
+        assert_eq!(repo_name("git@example.org:app"), "app");
+        assert_eq!(repo_name("file:///srv/git/tools/"), "tools");
+        assert_eq!(repo_name(".git"), "treemerge");
+    }
+}
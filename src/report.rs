@@ -0,0 +1,301 @@
+use crate::cli::{ListSort, PerFileStatsFormat, Tokenizer};
+use crate::lang;
+use crate::merge::human_size;
+use crate::style::Style;
+use crate::tokens;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Size, line and token counts of one selected file
+#[derive(Serialize)]
+pub struct FileStats {
+    pub path: String,
+    pub size: u64,
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+    pub tokens: usize,
+    /// Language from the file name, if recognized
+    pub language: Option<&'static str>,
+}
+
+impl FileStats {
+    pub fn measure(path: String, content: &str, tokenizer: Tokenizer) -> FileStats {
+        FileStats {
+            language: lang::language_for(Path::new(&path)),
+            size: content.len() as u64,
+            lines: content.lines().count(),
+            words: content.split_whitespace().count(),
+            chars: content.chars().count(),
+            tokens: tokens::count(tokenizer, content),
+            path,
+        }
+    }
+}
+
+/// Order a listing; paths ascend, everything else is largest first
+pub fn sort(stats: &mut [FileStats], key: ListSort) {
+    match key {
+        ListSort::Path => stats.sort_by(|a, b| a.path.cmp(&b.path)),
+        ListSort::Size => stats.sort_by_key(|s| Reverse(s.size)),
+        ListSort::Lines => stats.sort_by_key(|s| Reverse(s.lines)),
+        ListSort::Tokens => stats.sort_by_key(|s| Reverse(s.tokens)),
+    }
+}
+
+/// Print one row per file followed by totals
+pub fn print_listing(stats: &[FileStats], style: Style) {
+    println!("{:>10}  {:>8}  {:>8}  path", "size", "lines", "tokens");
+    for s in stats {
+        println!(
+            "{:>10}  {:>8}  {:>8}  {}",
+            human_size(s.size),
+            s.lines,
+            s.tokens,
+            style.included(&s.path)
+        );
+    }
+    let total = format!(
+        "{:>10}  {:>8}  {:>8}  total",
+        human_size(stats.iter().map(|s| s.size).sum()),
+        stats.iter().map(|s| s.lines).sum::<usize>(),
+        stats.iter().map(|s| s.tokens).sum::<usize>()
+    );
+    println!("{}", style.bold(&total));
+}
+
+/// The `--count-tokens` report: estimated tokens and share of the total per
+/// file, then the total
+pub fn print_tokens(stats: &[FileStats], tokenizer: Tokenizer, style: Style) {
+    let total: usize = stats.iter().map(|s| s.tokens).sum();
+    println!("{:>8}  {:>6}  path", "tokens", "share");
+    for s in stats {
+        let share = 100.0 * s.tokens as f64 / total.max(1) as f64;
+        println!("{:>8}  {:>5.1}%  {}", s.tokens, share, style.included(&s.path));
+    }
+    let name = tokenizer
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+    let line = format!("{:>8}  {:>6}  total ({} files, {} tokenizer)", total, "", stats.len(), name);
+    println!("{}", style.bold(&line));
+}
+
+/// The `--per-file-stats` section: lines, words, characters and tokens of
+/// every file, as an aligned table with totals or a JSON array
+pub fn per_file(stats: &[FileStats], format: PerFileStatsFormat) -> String {
+    if format == PerFileStatsFormat::Json {
+        let mut json = serde_json::to_string_pretty(stats).unwrap_or_default();
+        json.push('\n');
+        return json;
+    }
+    let row = |lines: usize, words: usize, chars: usize, tokens: usize, path: &str| {
+        format!("{:>8}  {:>8}  {:>10}  {:>8}  {}\n", lines, words, chars, tokens, path)
+    };
+    let mut out = format!("{:>8}  {:>8}  {:>10}  {:>8}  path\n", "lines", "words", "chars", "tokens");
+    for s in stats {
+        out.push_str(&row(s.lines, s.words, s.chars, s.tokens, &s.path));
+    }
+    out.push_str(&row(
+        stats.iter().map(|s| s.lines).sum(),
+        stats.iter().map(|s| s.words).sum(),
+        stats.iter().map(|s| s.chars).sum(),
+        stats.iter().map(|s| s.tokens).sum(),
+        "total",
+    ));
+    out
+}
+
+/// Print the `n` largest files by bytes, lines and tokens
+pub fn print_top(stats: &[FileStats], n: usize) {
+    let mut ranked: Vec<&FileStats> = stats.iter().collect();
+    for (name, key) in [
+        ("size", ListSort::Size),
+        ("lines", ListSort::Lines),
+        ("tokens", ListSort::Tokens),
+    ] {
+        let value = |s: &FileStats| match key {
+            ListSort::Size => human_size(s.size),
+            ListSort::Lines => s.lines.to_string(),
+            _ => s.tokens.to_string(),
+        };
+        ranked.sort_by_key(|s| match key {
+            ListSort::Size => Reverse(s.size),
+            ListSort::Lines => Reverse(s.lines as u64),
+            _ => Reverse(s.tokens as u64),
+        });
+        println!();
+        println!("Top {} by {}:", n.min(ranked.len()), name);
+        for s in ranked.iter().take(n) {
+            println!("  {:>10}  {}", value(s), s.path);
+        }
+    }
+}
+
+/// Upper bounds (exclusive) of the size histogram buckets
+const SIZE_BUCKETS: &[u64] = &[1 << 10, 4 << 10, 16 << 10, 64 << 10, 256 << 10, 1 << 20];
+/// Upper bounds (exclusive) of the line histogram buckets
+const LINE_BUCKETS: &[u64] = &[10, 50, 100, 500, 1000, 5000];
+/// Width of the longest histogram bar
+const BAR_WIDTH: usize = 40;
+
+/// Nearest-rank percentile of ascending `sorted`
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Print a bucketed histogram of `values`, labelling bounds with `fmt`
+fn print_histogram(title: &str, values: &[u64], bounds: &[u64], fmt: impl Fn(u64) -> String) {
+    let mut counts = vec![0usize; bounds.len() + 1];
+    for &v in values {
+        counts[bounds.iter().position(|&b| v < b).unwrap_or(bounds.len())] += 1;
+    }
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+
+    println!();
+    println!("{}:", title);
+    for (i, count) in counts.iter().enumerate() {
+        let label = match i {
+            0 => format!("< {}", fmt(bounds[0])),
+            i if i == bounds.len() => format!(">= {}", fmt(bounds[i - 1])),
+            i => format!("{} - {}", fmt(bounds[i - 1]), fmt(bounds[i])),
+        };
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(most));
+        println!("{}", format!("  {:>18}  {:>6}  {}", label, count, bar).trim_end());
+    }
+}
+
+/// Print size and line histograms plus percentiles of the selection
+pub fn print_stats(stats: &[FileStats]) {
+    let mut sizes: Vec<u64> = stats.iter().map(|s| s.size).collect();
+    let mut lines: Vec<u64> = stats.iter().map(|s| s.lines as u64).collect();
+    let mut tokens: Vec<u64> = stats.iter().map(|s| s.tokens as u64).collect();
+    sizes.sort_unstable();
+    lines.sort_unstable();
+    tokens.sort_unstable();
+
+    print_histogram("File sizes", &sizes, SIZE_BUCKETS, human_size);
+    print_histogram("Line counts", &lines, LINE_BUCKETS, |n| n.to_string());
+
+    println!();
+    println!("{:>10}  {:>10}  {:>8}  {:>8}", "percentile", "size", "lines", "tokens");
+    for p in [50, 75, 90, 95, 99, 100] {
+        let label = if p == 100 { "max".to_string() } else { format!("p{}", p) };
+        println!(
+            "{:>10}  {:>10}  {:>8}  {:>8}",
+            label,
+            human_size(percentile(&sizes, p)),
+            percentile(&lines, p),
+            percentile(&tokens, p)
+        );
+    }
+}
+
+/// Print files, lines, bytes and tokens per language, largest share first
+pub fn print_languages(stats: &[FileStats]) {
+    // (files, lines, bytes, tokens)
+    let mut by_lang: HashMap<&str, (usize, usize, u64, usize)> = HashMap::new();
+    for s in stats {
+        let e = by_lang.entry(s.language.unwrap_or("other")).or_default();
+        e.0 += 1;
+        e.1 += s.lines;
+        e.2 += s.size;
+        e.3 += s.tokens;
+    }
+    let mut rows: Vec<_> = by_lang.into_iter().collect();
+    rows.sort_by(|a, b| b.1.3.cmp(&a.1.3).then(a.0.cmp(b.0)));
+    let total_tokens: usize = rows.iter().map(|(_, r)| r.3).sum();
+
+    println!();
+    println!(
+        "{:<12}  {:>6}  {:>8}  {:>10}  {:>8}  {:>6}",
+        "language", "files", "lines", "bytes", "tokens", "%"
+    );
+    for (name, (files, lines, bytes, tokens)) in &rows {
+        let share = if total_tokens == 0 {
+            0.0
+        } else {
+            *tokens as f64 * 100.0 / total_tokens as f64
+        };
+        println!(
+            "{:<12}  {:>6}  {:>8}  {:>10}  {:>8}  {:>5.1}%",
+            name,
+            files,
+            lines,
+            human_size(*bytes),
+            tokens,
+            share
+        );
+    }
+    println!(
+        "{:<12}  {:>6}  {:>8}  {:>10}  {:>8}",
+        "total",
+        stats.len(),
+        stats.iter().map(|s| s.lines).sum::<usize>(),
+        human_size(stats.iter().map(|s| s.size).sum()),
+        total_tokens
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_orders() {
+        let mut stats = vec![
+            FileStats::measure("b.rs".into(), "x\n", Tokenizer::Chars),
+            FileStats::measure("a.rs".into(), "one\ntwo\nthree\n", Tokenizer::Chars),
+            FileStats::measure("c.rs".into(), &"word ".repeat(20), Tokenizer::Chars),
+        ];
+        assert_eq!((stats[1].size, stats[1].lines, stats[1].tokens), (14, 3, 4));
+        let paths = |stats: &[FileStats]| stats.iter().map(|s| s.path.clone()).collect::<Vec<_>>();
+        sort(&mut stats, ListSort::Path);
+        assert_eq!(paths(&stats), ["a.rs", "b.rs", "c.rs"]);
+        sort(&mut stats, ListSort::Size);
+        assert_eq!(paths(&stats), ["c.rs", "a.rs", "b.rs"]);
+        sort(&mut stats, ListSort::Lines);
+        assert_eq!(paths(&stats), ["a.rs", "c.rs", "b.rs"]);
+        sort(&mut stats, ListSort::Tokens);
+        assert_eq!(paths(&stats), ["c.rs", "a.rs", "b.rs"]);
+    }
+
+    #[test]
+    fn percentiles() {
+        let values: Vec<u64> = (1..=10).collect();
+        assert_eq!(percentile(&values, 50), 5);
+        assert_eq!(percentile(&values, 95), 10);
+        assert_eq!(percentile(&values, 1), 1);
+        assert_eq!(percentile(&[7], 50), 7);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[test]
+    fn languages_from_names() {
+        assert_eq!(FileStats::measure("src/a.rs".into(), "", Tokenizer::Chars).language, Some("rust"));
+        assert_eq!(FileStats::measure("notes".into(), "", Tokenizer::Chars).language, None);
+    }
+
+    #[test]
+    fn per_file_tables_and_json() {
+        let stats = [
+            FileStats::measure("a.rs".into(), "one two\nthree\n", Tokenizer::Chars),
+            FileStats::measure("b.rs".into(), "é\n", Tokenizer::Chars),
+        ];
+        assert_eq!((stats[0].words, stats[0].chars, stats[1].chars), (3, 14, 2));
+        let table = per_file(&stats, PerFileStatsFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "   lines     words       chars    tokens  path");
+        assert_eq!(lines[3], "       3         4          16         5  total");
+        let json: serde_json::Value = serde_json::from_str(&per_file(&stats, PerFileStatsFormat::Json)).unwrap();
+        assert_eq!(json[1]["path"], "b.rs");
+        assert_eq!(json[0]["words"], 3);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::cli::SubmoduleMode;
+use crate::remote::{self, Checkout};
+use crate::settings::Settings;
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One input tree of a merge
+pub struct Root {
+    /// Prefix for header paths, set when merging several repositories
+    pub name: Option<String>,
+    pub path: PathBuf,
+    /// Where the tree came from, as given (path or URL)
+    pub source: String,
+    /// Keeps a cloned remote alive for the duration of the merge
+    _checkout: Option<Arc<Checkout>>,
+}
+
+/// `workspaces.toml`: the repositories making up one project
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspaceFile {
+    repo: Vec<RepoSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RepoSpec {
+    name: Option<String>,
+    /// Local directory, relative to the workspaces file
+    path: Option<PathBuf>,
+    /// Git URL or `github:owner/repo`
+    url: Option<String>,
+    depth: Option<u32>,
+    #[serde(default)]
+    remote_path: Vec<String>,
+}
+
+/// Clone `source` if it is remote (with `depth` and `remote_path`), otherwise
+/// use it as a directory
+fn open_one(
+    name: Option<String>,
+    source: &str,
+    depth: Option<u32>,
+    remote_path: &[String],
+    args: &Settings,
+) -> Result<Root> {
+    let verbose = args.verbose;
+    let (path, checkout) = match remote::parse(Path::new(source)) {
+        Some(url) => {
+            let checkout = remote::fetch_shared(&url, depth, remote_path, verbose, &args.diagnostics)?;
+            (checkout.path().to_path_buf(), Some(checkout))
+        }
+        None => (PathBuf::from(source), None),
+    };
+
+    // Only directories allowed
+    if !path.is_dir() {
+        return Err(anyhow!("treemerge only operates on directories: {}", source));
+    }
+    if args.fetch_submodules && args.submodules == SubmoduleMode::Include {
+        remote::fetch_submodules(&path, verbose, &args.diagnostics)?;
+    }
+    Ok(Root {
+        name,
+        path,
+        source: source.to_owned(),
+        _checkout: checkout,
+    })
+}
+
+/// `NAME=PATH` names a repository explicitly; URLs are left alone
+fn split_name(spec: &str) -> (Option<&str>, &str) {
+    match spec.split_once('=') {
+        Some((name, source)) if !name.is_empty() && !name.contains(['/', '\\', ':']) => {
+            (Some(name), source)
+        }
+        _ => (None, spec),
+    }
+}
+
+/// Name of a tree: the repository of a URL, or the directory
+pub fn default_name(root: &Root) -> String {
+    match remote::parse(Path::new(&root.source)) {
+        Some(url) => remote::repo_name(&url).to_owned(),
+        None => fs::canonicalize(&root.path)
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "repo".into()),
+    }
+}
+
+/// Input trees from the PATH argument, `--repo` or `--workspaces`
+pub fn open(args: &Settings) -> Result<Vec<Root>> {
+    let mut roots = Vec::new();
+    if let Some(file) = &args.workspaces {
+        let text = fs::read_to_string(file)
+            .with_context(|| format!("cannot read {}", file.display()))?;
+        let spec: WorkspaceFile =
+            toml::from_str(&text).with_context(|| format!("invalid {}", file.display()))?;
+        let base = file.parent().unwrap_or(Path::new(""));
+        for repo in spec.repo {
+            let source = match (&repo.path, &repo.url) {
+                (Some(path), None) => base.join(path).to_string_lossy().into_owned(),
+                (None, Some(url)) => url.clone(),
+                _ => return Err(anyhow!("each [[repo]] needs exactly one of path or url")),
+            };
+            let root = open_one(repo.name, &source, repo.depth, &repo.remote_path, args)?;
+            roots.push(root);
+        }
+    }
+    for spec in &args.repo {
+        let (name, source) = split_name(spec);
+        let root = open_one(
+            name.map(str::to_owned),
+            source,
+            args.depth,
+            &args.remote_path,
+            args,
+        )?;
+        roots.push(root);
+    }
+
+    if roots.is_empty() {
+        let path = args
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("no input: give a PATH, --repo or --workspaces"))?;
+        if remote::parse(path).is_none() && (args.depth.is_some() || !args.remote_path.is_empty()) {
+            return Err(anyhow!("--depth and --remote-path only apply to remote inputs"));
+        }
+        let source = path.to_string_lossy();
+        return Ok(vec![open_one(
+            None,
+            &source,
+            args.depth,
+            &args.remote_path,
+            args,
+        )?]);
+    }
+
+    // Several trees: every one gets a unique name to prefix its paths
+    let mut used = HashSet::new();
+    for root in &mut roots {
+        let base = root.name.take().unwrap_or_else(|| default_name(root));
+        let mut name = base.clone();
+        let mut n = 2;
+        while !used.insert(name.clone()) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        root.name = Some(name);
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_given_with_the_source() {
+        assert_eq!(split_name("api=../api"), (Some("api"), "../api"));
+        assert_eq!(split_name("../api"), (None, "../api"));
+        assert_eq!(split_name("=../api"), (None, "=../api"));
+        assert_eq!(split_name("https://host/x?a=b"), (None, "https://host/x?a=b"));
+        assert_eq!(split_name("dir/a=b"), (None, "dir/a=b"));
+    }
+}
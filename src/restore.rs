@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// `path` (as a header names it) as a relative path of plain components,
+/// or None when it could land outside the directory it is restored to
+pub fn relative(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let safe = path.components().all(|c| matches!(c, Component::Normal(_)));
+    (safe && path.components().next().is_some()).then(|| path.to_path_buf())
+}
+
+/// `rel` (as `relative` returns it) below `root`, a canonical directory,
+/// refusing to go through symlinks: a link already under `root` could point
+/// anywhere. With `create`, the missing directories are made on the way.
+pub fn resolve(root: &Path, rel: &Path, create: bool) -> Result<PathBuf> {
+    let mut path = root.to_path_buf();
+    let mut parts = rel.components().peekable();
+    while let Some(part) = parts.next() {
+        let last = parts.peek().is_none();
+        path.push(part);
+        match fs::symlink_metadata(&path) {
+            Ok(m) if m.file_type().is_symlink() => return Err(anyhow!("{} is a symlink", path.display())),
+            Ok(m) if !last && !m.is_dir() => return Err(anyhow!("{} is not a directory", path.display())),
+            Ok(_) => {}
+            // Nothing below a missing directory exists either
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !create => break,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if !last {
+                    fs::create_dir(&path).with_context(|| format!("cannot create {}", path.display()))?;
+                }
+            }
+            Err(e) => return Err(e).with_context(|| format!("cannot read {}", path.display())),
+        }
+    }
+    let path = root.join(rel);
+    // Whatever the checks above missed, the file's directory must be below root
+    if let Some(parent) = path.parent().filter(|p| p.exists()) {
+        let parent = fs::canonicalize(parent).with_context(|| format!("cannot read {}", parent.display()))?;
+        if !parent.starts_with(root) {
+            return Err(anyhow!("{} is outside {}", path.display(), root.display()));
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(relative("src/a.rs"), Some(PathBuf::from("src/a.rs")));
+        assert_eq!(relative("./a.rs"), None);
+        assert_eq!(relative("a/../b"), None);
+        assert_eq!(relative("../a"), None);
+        assert_eq!(relative("/etc/passwd"), None);
+        assert_eq!(relative(""), None);
+    }
+
+    #[test]
+    fn missing_directories_are_created_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let rel = Path::new("a/b/c.txt");
+        assert_eq!(resolve(&root, rel, false).unwrap(), root.join(rel));
+        assert!(!root.join("a").exists());
+        assert_eq!(resolve(&root, rel, true).unwrap(), root.join(rel));
+        assert!(root.join("a/b").is_dir());
+        assert!(!root.join(rel).exists());
+    }
+
+    #[test]
+    fn files_in_the_way_of_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("a"), "a file").unwrap();
+        assert!(resolve(&root, Path::new("a/b.txt"), true).is_err());
+        assert_eq!(fs::read_to_string(root.join("a")).unwrap(), "a file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = fs::canonicalize(dir.path()).unwrap();
+        let (root, victim) = (base.join("out"), base.join("victim"));
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&victim).unwrap();
+        std::os::unix::fs::symlink(&victim, root.join("src")).unwrap();
+        std::os::unix::fs::symlink(victim.join("file.txt"), root.join("file.txt")).unwrap();
+
+        for rel in ["src/evil.txt", "src/deep/evil.txt", "file.txt"] {
+            assert!(resolve(&root, Path::new(rel), false).is_err(), "{rel}");
+            assert!(resolve(&root, Path::new(rel), true).is_err(), "{rel}");
+        }
+        assert_eq!(fs::read_dir(&victim).unwrap().count(), 0);
+    }
+}
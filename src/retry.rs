@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// How often and how patiently to retry files that fail to open or read
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries while another process holds the file
+    pub retries: u32,
+    pub delay: Duration,
+    /// Retries on transient I/O errors, each waiting twice as long as the last
+    pub io_retries: u32,
+    pub io_delay: Duration,
+}
+
+/// Whether an error means another process currently holds the file
+///
+/// Editors and virus scanners on Windows routinely open files without
+/// sharing; the lock is usually released within moments.
+pub fn is_locked(err: &io::Error) -> bool {
+    if cfg!(windows) {
+        // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        matches!(err.raw_os_error(), Some(32) | Some(33))
+    } else {
+        false
+    }
+}
+
+/// Whether an error may go away on its own, as when an NFS or SMB server
+/// is briefly unreachable or a file handle went stale
+pub fn is_transient(err: &io::Error) -> bool {
+    if err.kind() == io::ErrorKind::TimedOut {
+        return true;
+    }
+    let Some(code) = err.raw_os_error() else {
+        return false;
+    };
+    if cfg!(windows) {
+        // ERROR_UNEXP_NET_ERR, ERROR_NETWORK_BUSY, ERROR_NETNAME_DELETED,
+        // ERROR_SEM_TIMEOUT
+        matches!(code, 59 | 54 | 64 | 121)
+    } else if cfg!(target_os = "macos") {
+        // EIO, EAGAIN, ETIMEDOUT, ESTALE
+        matches!(code, 5 | 35 | 60 | 70)
+    } else {
+        // EIO, EAGAIN, ETIMEDOUT, ESTALE
+        matches!(code, 5 | 11 | 110 | 116)
+    }
+}
+
+/// Whether a file failing with `err` is skipped rather than ending the run
+pub fn skippable(err: &io::Error) -> bool {
+    is_locked(err) || is_transient(err)
+}
+
+impl RetryPolicy {
+    /// Wait before transient retry number `attempt` (from 0)
+    fn backoff(&self, attempt: u32) {
+        thread::sleep(self.io_delay.saturating_mul(1 << attempt.min(16)));
+    }
+
+    fn open_file(&self, path: &Path) -> io::Result<File> {
+        let (mut locked, mut transient) = (0, 0);
+        loop {
+            match File::open(path) {
+                Err(e) if is_locked(&e) && locked < self.retries => {
+                    locked += 1;
+                    thread::sleep(self.delay);
+                }
+                Err(e) if is_transient(&e) && transient < self.io_retries => {
+                    self.backoff(transient);
+                    transient += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Open `path`, retrying while it is locked or unreachable
+    ///
+    /// Reads from the result retry transient errors too, reopening the
+    /// file where they left off.
+    pub fn open(&self, path: &Path) -> io::Result<Retrying> {
+        Ok(Retrying {
+            file: self.open_file(path)?,
+            path: path.to_path_buf(),
+            pos: 0,
+            policy: *self,
+        })
+    }
+
+    /// Skip report entry for a file given up on with `err` (see `skippable`)
+    pub fn skip_reason(&self, err: &io::Error) -> String {
+        if is_locked(err) {
+            format!("locked by another process after {} retries", self.retries)
+        } else {
+            format!("transient I/O error after {} retries: {}", self.io_retries, err)
+        }
+    }
+}
+
+/// File reader that rides out transient errors
+pub struct Retrying {
+    file: File,
+    path: PathBuf,
+    pos: u64,
+    policy: RetryPolicy,
+}
+
+impl Read for Retrying {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.file.read(buf) {
+                Ok(n) => {
+                    self.pos += n as u64;
+                    return Ok(n);
+                }
+                Err(e) => e,
+            };
+            if !is_transient(&err) || attempt >= self.policy.io_retries {
+                return Err(err);
+            }
+            self.policy.backoff(attempt);
+            attempt += 1;
+            // A stale handle stays stale; start over from a fresh one
+            match File::open(&self.path) {
+                Ok(mut file) => {
+                    file.seek(SeekFrom::Start(self.pos))?;
+                    self.file = file;
+                }
+                Err(e) if is_transient(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn locks_and_transient_errors() {
+        let sharing = io::Error::from_raw_os_error(32);
+        assert_eq!(is_locked(&sharing), cfg!(windows));
+        assert!(!is_locked(&io::Error::from(io::ErrorKind::NotFound)));
+        // ESTALE and ETIMEDOUT
+        assert!(is_transient(&io::Error::from_raw_os_error(116)));
+        assert!(is_transient(&io::Error::from(io::ErrorKind::TimedOut)));
+        assert!(!is_transient(&io::Error::from(io::ErrorKind::NotFound)));
+        assert!(skippable(&io::Error::from_raw_os_error(5)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let policy = RetryPolicy { retries: 3, delay: Duration::from_secs(10), io_retries: 2, io_delay: Duration::from_secs(10) };
+        let err = policy.open(&dir.path().join("missing")).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        std::fs::write(dir.path().join("a"), "a").unwrap();
+        let mut text = String::new();
+        policy.open(&dir.path().join("a")).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "a");
+        assert_eq!(policy.skip_reason(&io::Error::from_raw_os_error(5)), "transient I/O error after 2 retries: Input/output error (os error 5)");
+    }
+}
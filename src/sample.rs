@@ -0,0 +1,98 @@
+use crate::cli::SampleStrata;
+use crate::lang;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Stable pseudo-random rank of `path` for `seed`
+///
+/// Hashing instead of drawing from a generator keeps a file's chance
+/// independent of the rest of the tree and of the order it was scanned in.
+fn rank(seed: u64, path: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_le_bytes());
+    hasher.update(path.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Stratum of a (`/`-separated) path
+fn stratum(path: &str, strata: SampleStrata) -> String {
+    match strata {
+        SampleStrata::Random => String::new(),
+        SampleStrata::Directory => match path.split_once('/') {
+            Some((top, _)) => top.to_string(),
+            None => ".".to_string(),
+        },
+        SampleStrata::Language => lang::language_for(Path::new(path)).unwrap_or("other").to_string(),
+    }
+}
+
+/// Split `count` over strata of the given sizes
+///
+/// Every stratum gets one file while there are enough to go round (the
+/// largest strata first otherwise); the rest is shared in proportion to
+/// size, handing leftovers to the largest remainders.
+fn quotas(sizes: &[usize], count: usize) -> Vec<usize> {
+    let mut by_size: Vec<usize> = (0..sizes.len()).collect();
+    by_size.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+    let mut quotas = vec![0; sizes.len()];
+    if count < sizes.len() {
+        for &i in &by_size[..count] {
+            quotas[i] = 1;
+        }
+        return quotas;
+    }
+
+    let spare = count - sizes.len();
+    let pool: usize = sizes.iter().map(|s| s - 1).sum();
+    let mut remainders = Vec::new();
+    for (i, &size) in sizes.iter().enumerate() {
+        let share = spare * (size - 1);
+        quotas[i] = 1 + share / pool.max(1);
+        remainders.push((share % pool.max(1), i));
+    }
+    let left = count - quotas.iter().sum::<usize>();
+    remainders.sort_by_key(|&(r, i)| (std::cmp::Reverse(r), i));
+    for &(_, i) in remainders.iter().take(left) {
+        quotas[i] += 1;
+    }
+    quotas
+}
+
+/// Indices of a reproducible sample of `count` of `paths`
+pub fn select(paths: &[String], count: usize, seed: u64, strata: SampleStrata) -> HashSet<usize> {
+    if count >= paths.len() {
+        return (0..paths.len()).collect();
+    }
+
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, p) in paths.iter().enumerate() {
+        groups.entry(stratum(p, strata)).or_default().push(i);
+    }
+    let sizes: Vec<usize> = groups.values().map(Vec::len).collect();
+
+    let mut picked = HashSet::new();
+    for (mut members, quota) in groups.into_values().zip(quotas(&sizes, count)) {
+        members.sort_by_cached_key(|&i| rank(seed, &paths[i]));
+        picked.extend(members.into_iter().take(quota));
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stratified_and_reproducible() {
+        assert_eq!(quotas(&[6, 2, 2], 5), [3, 1, 1]);
+        assert_eq!(quotas(&[6, 2, 2], 2), [1, 1, 0]);
+        let paths: Vec<String> = (0..20).map(|i| format!("{}/f{}.rs", if i < 15 { "src" } else { "docs" }, i)).collect();
+        let picked = select(&paths, 4, 7, SampleStrata::Directory);
+        assert_eq!(picked.len(), 4);
+        assert_eq!(picked, select(&paths, 4, 7, SampleStrata::Directory));
+        assert!(picked.iter().any(|&i| paths[i].starts_with("docs/")));
+        assert_ne!(select(&paths, 4, 7, SampleStrata::Random), select(&paths, 4, 8, SampleStrata::Random));
+        assert_eq!(select(&paths, 30, 7, SampleStrata::Random).len(), 20);
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/b-rodrigues/treemerge/releases/latest";
+/// Release binaries are far smaller; this only bounds a misbehaving server
+const MAX_DOWNLOAD: u64 = 256 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Suffix of this platform's release binary (`x86_64-linux`, `aarch64-darwin`, ...)
+fn platform() -> Option<String> {
+    let os = match env::consts::OS {
+        "linux" => "linux",
+        "macos" => "darwin",
+        _ => return None,
+    };
+    Some(format!("{}-{}", env::consts::ARCH, os))
+}
+
+/// `1.2.3` as numbers, ignoring a leading `v` and any pre-release suffix
+fn version(s: &str) -> Vec<u64> {
+    s.trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|p| p.parse().ok())
+        .collect()
+}
+
+fn get(url: &str) -> Result<Vec<u8>> {
+    let mut response = ureq::get(url)
+        .header("User-Agent", concat!("treemerge/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| anyhow!("cannot fetch {}: {}", url, e))?;
+    response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD)
+        .read_to_vec()
+        .map_err(|e| anyhow!("cannot read {}: {}", url, e))
+}
+
+/// Replace `exe` with `binary`, going through a file in the same directory
+/// so the swap is a single rename
+fn replace(exe: &Path, binary: &[u8]) -> Result<()> {
+    let dir = exe.parent().ok_or_else(|| anyhow!("cannot locate {}", exe.display()))?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)
+        .with_context(|| format!("cannot write to {} (installed by a package manager?)", dir.display()))?;
+    tmp.write_all(binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o755))?;
+    }
+    tmp.persist(exe)
+        .map_err(|e| anyhow!("cannot replace {}: {}", exe.display(), e.error))?;
+    Ok(())
+}
+
+/// Update the running binary to the latest GitHub release
+///
+/// The download must match the `.sha256` published next to it. With
+/// `check`, only report whether an update is available.
+pub fn self_update(check: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release: Release = serde_json::from_slice(&get(LATEST_RELEASE)?)
+        .context("unexpected response from GitHub")?;
+    if version(&release.tag_name) <= version(current) {
+        println!("treemerge {} is up to date (latest release: {})", current, release.tag_name);
+        return Ok(());
+    }
+    if check {
+        println!("treemerge {} is available (installed: {})", release.tag_name, current);
+        return Ok(());
+    }
+
+    let platform = platform().ok_or_else(|| anyhow!("no prebuilt binaries for this platform"))?;
+    let name = format!("treemerge-{}", platform);
+    let asset = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| anyhow!("release {} has no {}", release.tag_name, name))
+    };
+    let binary_asset = asset(&name)?;
+    let checksum_asset = asset(&format!("{}.sha256", name))?;
+
+    let binary = get(&binary_asset.browser_download_url)?;
+    let expected = String::from_utf8_lossy(&get(&checksum_asset.browser_download_url)?)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let actual = format!("{:x}", Sha256::digest(&binary));
+    if actual != expected {
+        return Err(anyhow!(
+            "checksum mismatch for {} (expected {}, got {}); not updating",
+            name,
+            expected,
+            actual
+        ));
+    }
+
+    let exe = env::current_exe()?.canonicalize()?;
+    replace(&exe, &binary)?;
+    println!("updated {} from {} to {}", exe.display(), current, release.tag_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_and_replacement() {
+        assert!(version("v0.0.10") > version("0.0.9"));
+        assert_eq!(version("v1.2.3-rc.1"), [1, 2, 3]);
+        assert!(version("v1.0.0") <= version("1.0.0"));
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("treemerge");
+        fs::write(&exe, "old").unwrap();
+        replace(&exe, b"new").unwrap();
+        assert_eq!(fs::read(&exe).unwrap(), b"new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
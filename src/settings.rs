@@ -0,0 +1,277 @@
+use crate::cli::{
+    ColorChoice, DepGraphFormat, Delimiters, ExcludeCategory, FileKind, HeaderStyle, LfsMode,
+    ListSort, MaxFilesOrder, OutputEncoding, OutputFormat, Overflow, PerFileStatsFormat,
+    ProgressFormat, RootLabel, Sample, SampleStrata, SecretScan, SortOrder, SubmoduleMode,
+    TimestampMode, TocDetail, Tokenizer, TrimStrategy, UploadMethod,
+};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Where a merge sends its warnings, notes and progress events; standard
+/// error by default
+#[derive(Clone)]
+pub struct Diagnostics(Arc<dyn Fn(&str) + Send + Sync>);
+
+impl Diagnostics {
+    /// Pass every message to `sink`, one line or block at a time
+    pub fn new(sink: impl Fn(&str) + Send + Sync + 'static) -> Diagnostics {
+        Diagnostics(Arc::new(sink))
+    }
+
+    pub fn emit(&self, message: impl fmt::Display) {
+        (self.0)(&message.to_string())
+    }
+}
+
+impl Default for Diagnostics {
+    fn default() -> Diagnostics {
+        Diagnostics::new(|message| eprintln!("{}", message))
+    }
+}
+
+impl fmt::Debug for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Diagnostics")
+    }
+}
+
+/// Everything a merge is told to do, apart from the command line that
+/// usually says it
+///
+/// The fields are the merge options of `Args` under the same names, once
+/// `--no-redact` and `--use-gitignore` are settled; `Default` is a merge
+/// with no options given.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub path: Option<PathBuf>,
+    pub repo: Vec<String>,
+    pub workspaces: Option<PathBuf>,
+    pub output_per_root: bool,
+    pub root_label: RootLabel,
+    pub output: Option<PathBuf>,
+    pub stdout: bool,
+    pub include: Vec<String>,
+    pub ranges_from: Option<PathBuf>,
+    pub exclude: Vec<String>,
+    pub ext: Vec<String>,
+    pub only: Vec<FileKind>,
+    pub all_files: bool,
+    pub no_default_exclude: Vec<ExcludeCategory>,
+    pub default_exclude_only: Vec<ExcludeCategory>,
+    pub no_gitignore: bool,
+    pub lfs: LfsMode,
+    pub submodules: SubmoduleMode,
+    pub fetch_submodules: bool,
+    pub lfs_smudge: bool,
+    pub split_every: Option<usize>,
+    pub max_tokens: Option<usize>,
+    pub header_style: HeaderStyle,
+    pub diff_previous: bool,
+    pub format: OutputFormat,
+    pub delimiters: Delimiters,
+    pub output_encoding: OutputEncoding,
+    pub git_preamble: bool,
+    pub git_meta: bool,
+    pub blame_summary: bool,
+    pub git_status: bool,
+    pub diff: Option<String>,
+    pub diff_full_under: Option<usize>,
+    pub changed_with_context: Option<String>,
+    pub manifest: Option<PathBuf>,
+    pub manifest_sign_key: Option<PathBuf>,
+    pub xattrs: Vec<String>,
+    pub report_skipped: Option<PathBuf>,
+    pub deps_summary: bool,
+    pub capture: Vec<String>,
+    pub sort: SortOrder,
+    pub order_file: Option<PathBuf>,
+    pub entry_points: bool,
+    pub dep_graph: Option<DepGraphFormat>,
+    pub per_file_stats: Option<PerFileStatsFormat>,
+    pub toc: Option<TocDetail>,
+    pub dir_summaries: bool,
+    pub instructions: Option<PathBuf>,
+    pub prompt: Option<String>,
+    pub chunks: Option<PathBuf>,
+    pub stable_ids: bool,
+    pub chunk_tokens: usize,
+    pub explode: Option<PathBuf>,
+    pub template: Option<PathBuf>,
+    pub upload_url: Option<String>,
+    pub upload_method: UploadMethod,
+    pub upload_header: Vec<String>,
+    pub upload_token: Option<String>,
+    pub open: bool,
+    pub pager: bool,
+    pub color: ColorChoice,
+    pub dry_run: bool,
+    pub progress_format: ProgressFormat,
+    pub count_tokens: bool,
+    pub explain: bool,
+    pub max_output_size: u64,
+    pub probe_dirs: Option<u64>,
+    pub skip_from: Option<PathBuf>,
+    pub redact_pii: bool,
+    pub redact: bool,
+    pub scan_secrets: Option<SecretScan>,
+    pub no_confirm: bool,
+    pub native_separators: bool,
+    pub owned_by: Option<String>,
+    pub perm: Option<u32>,
+    pub follow_symlinks: bool,
+    pub metadata: bool,
+    pub timestamp: TimestampMode,
+    pub include_merged: bool,
+    pub basename_hints: bool,
+    pub symlink_stubs: bool,
+    pub scan_checkpoint: Option<PathBuf>,
+    pub resume_scan: bool,
+    pub files_from: Option<PathBuf>,
+    pub package: Option<String>,
+    pub tokenizer: Tokenizer,
+    pub list_sort: Option<ListSort>,
+    pub digest: bool,
+    pub summarize_large: Option<String>,
+    pub summarize_threshold: u64,
+    pub budget_tokens: Option<usize>,
+    pub trim_strategy: TrimStrategy,
+    pub max_file_tokens: Option<usize>,
+    pub overflow: Overflow,
+    pub max_files: Option<usize>,
+    pub max_files_by: MaxFilesOrder,
+    pub sample: Option<Sample>,
+    pub sample_by: SampleStrata,
+    pub stats: bool,
+    pub top: Option<usize>,
+    pub depth: Option<u32>,
+    pub remote_path: Vec<String>,
+    pub io_limit: Option<f64>,
+    pub nice_io: bool,
+    pub lock_retries: u32,
+    pub lock_retry_delay: u64,
+    pub io_retries: u32,
+    pub io_retry_delay: u64,
+    pub debug_globs: bool,
+    pub verbose: bool,
+    /// Set by library callers; the command line prints to standard error
+    pub diagnostics: Diagnostics,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            path: None,
+            repo: Vec::new(),
+            workspaces: None,
+            output_per_root: false,
+            root_label: RootLabel::Dir,
+            output: None,
+            stdout: false,
+            include: Vec::new(),
+            ranges_from: None,
+            exclude: Vec::new(),
+            ext: Vec::new(),
+            only: Vec::new(),
+            all_files: false,
+            no_default_exclude: Vec::new(),
+            default_exclude_only: Vec::new(),
+            no_gitignore: false,
+            lfs: LfsMode::Note,
+            submodules: SubmoduleMode::Include,
+            fetch_submodules: false,
+            lfs_smudge: false,
+            split_every: None,
+            max_tokens: None,
+            header_style: HeaderStyle::Hash,
+            diff_previous: false,
+            format: OutputFormat::Text,
+            delimiters: Delimiters::Safe,
+            output_encoding: OutputEncoding::Utf8,
+            git_preamble: false,
+            git_meta: false,
+            blame_summary: false,
+            git_status: false,
+            diff: None,
+            diff_full_under: None,
+            changed_with_context: None,
+            manifest: None,
+            manifest_sign_key: None,
+            xattrs: Vec::new(),
+            report_skipped: None,
+            deps_summary: false,
+            capture: Vec::new(),
+            sort: SortOrder::Path,
+            order_file: None,
+            entry_points: false,
+            dep_graph: None,
+            per_file_stats: None,
+            toc: None,
+            dir_summaries: false,
+            instructions: None,
+            prompt: None,
+            chunks: None,
+            stable_ids: false,
+            chunk_tokens: 512,
+            explode: None,
+            template: None,
+            upload_url: None,
+            upload_method: UploadMethod::Post,
+            upload_header: Vec::new(),
+            upload_token: None,
+            open: false,
+            pager: false,
+            color: ColorChoice::Auto,
+            dry_run: false,
+            progress_format: ProgressFormat::Bar,
+            count_tokens: false,
+            explain: false,
+            max_output_size: 500 * 1024 * 1024,
+            probe_dirs: None,
+            skip_from: None,
+            redact_pii: false,
+            redact: false,
+            scan_secrets: None,
+            no_confirm: false,
+            native_separators: false,
+            owned_by: None,
+            perm: None,
+            follow_symlinks: false,
+            metadata: false,
+            timestamp: TimestampMode::None,
+            include_merged: false,
+            basename_hints: false,
+            symlink_stubs: false,
+            scan_checkpoint: None,
+            resume_scan: false,
+            files_from: None,
+            package: None,
+            tokenizer: Tokenizer::Approx,
+            list_sort: None,
+            digest: false,
+            summarize_large: None,
+            summarize_threshold: 100 * 1024,
+            budget_tokens: None,
+            trim_strategy: TrimStrategy::DropLowestPriority,
+            max_file_tokens: None,
+            overflow: Overflow::Skip,
+            max_files: None,
+            max_files_by: MaxFilesOrder::Priority,
+            sample: None,
+            sample_by: SampleStrata::Random,
+            stats: false,
+            top: None,
+            depth: None,
+            remote_path: Vec::new(),
+            io_limit: None,
+            nice_io: false,
+            lock_retries: 3,
+            lock_retry_delay: 250,
+            io_retries: 3,
+            io_retry_delay: 200,
+            debug_globs: false,
+            verbose: false,
+            diagnostics: Diagnostics::default(),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::settings::Diagnostics;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// A file that was selected but left out of the output
+pub struct Skipped {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Files dropped while writing, reported once the merge is done
+#[derive(Default)]
+pub struct SkipReport {
+    pub entries: Vec<Skipped>,
+}
+
+impl SkipReport {
+    /// Record a skipped file; later reasons for the same path are ignored
+    pub fn add(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        let path = path.into();
+        if !self.contains(&path) {
+            self.entries.push(Skipped {
+                path,
+                reason: reason.into(),
+            });
+        }
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.iter().any(|s| s.path == path)
+    }
+
+    /// Report what was skipped, if anything
+    pub fn print_summary(&self, diagnostics: &Diagnostics) {
+        if self.entries.is_empty() {
+            return;
+        }
+        diagnostics.emit(format_args!("warning: skipped {} file(s):", self.entries.len()));
+        for s in &self.entries {
+            diagnostics.emit(format_args!("  {} ({})", s.path, s.reason));
+        }
+    }
+}
+
+/// Paths to leave out whatever the filters say, from `--skip-from`
+///
+/// One root-relative path per line; anything after a tab is ignored, so a
+/// `--report-skipped` file can be fed back. A trailing `/` names a whole
+/// directory. Blank lines and `#` comments are skipped.
+pub struct SkipList {
+    files: HashSet<String>,
+    dirs: Vec<String>,
+}
+
+impl SkipList {
+    pub fn load(path: &Path) -> Result<SkipList> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("cannot read skip list {}", path.display()))?;
+        let mut list = SkipList {
+            files: HashSet::new(),
+            dirs: Vec::new(),
+        };
+        for line in text.lines() {
+            let entry = line.split('\t').next().unwrap_or_default().trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            // Reports written with --native-separators
+            let entry = if cfg!(windows) { entry.replace('\\', "/") } else { entry.to_string() };
+            let entry = entry.trim_start_matches("./");
+            match entry.strip_suffix('/') {
+                Some(dir) => list.dirs.push(dir.to_string()),
+                None => {
+                    list.files.insert(entry.to_string());
+                }
+            }
+        }
+        Ok(list)
+    }
+
+    /// Whether root-relative, `/`-separated `rel` is listed, itself or
+    /// through a directory
+    pub fn contains(&self, rel: &str) -> bool {
+        self.files.contains(rel)
+            || self
+                .dirs
+                .iter()
+                .any(|d| rel.strip_prefix(d.as_str()).is_some_and(|r| r.starts_with('/')))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_files_are_recorded() {
+        let mut report = SkipReport::default();
+        assert!(!report.contains("a.txt"));
+        report.add("a.txt", "locked by another process after 3 retries");
+        assert!(report.contains("a.txt") && !report.contains("b.txt"));
+        assert_eq!(report.entries[0].reason, "locked by another process after 3 retries");
+    }
+
+    #[test]
+    fn first_reason_wins() {
+        let mut report = SkipReport::default();
+        report.add("a.txt", "locked");
+        report.add("a.txt", "unreadable");
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].reason, "locked");
+    }
+
+    #[test]
+    fn skip_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("skip.txt");
+        fs::write(&file, "# left out\n./a.txt\tmatched --exclude\n\nvendor/\nb/c.txt\n").unwrap();
+        let list = SkipList::load(&file).unwrap();
+        assert!(list.contains("a.txt") && list.contains("b/c.txt"));
+        assert!(list.contains("vendor/x/y.rs") && !list.contains("vendor") && !list.contains("vendored/x.rs"));
+        assert!(!list.contains("b/c.txt.bak") && !list.contains("# left out"));
+        assert!(SkipList::load(&dir.path().join("missing")).is_err());
+    }
+}
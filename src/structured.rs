@@ -0,0 +1,115 @@
+use crate::cli::OutputFormat;
+use crate::template::TemplateFile;
+use anyhow::Result;
+use serde::Serialize;
+
+/// How `--format json` outputs start, ahead of the manifest's fields
+pub const JSON_START: &str = "{\n  \"manifest\": {\n    \"tool\": \"treemerge\",";
+
+/// How `--format jsonl` outputs start: the manifest record
+pub const JSONL_START: &str = "{\"type\":\"manifest\",\"tool\":\"treemerge\",";
+
+/// The selection a merge applied
+#[derive(Serialize)]
+pub struct Filters {
+    /// `--include` globs, line ranges left off
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub ext: Vec<String>,
+    /// `--only` kinds
+    pub only: Vec<String>,
+    /// Built-in and configured excludes in effect
+    pub default_excludes: Vec<String>,
+    pub gitignore: bool,
+    /// `--diff` or `--changed-with-context` revision
+    pub changed_since: Option<String>,
+}
+
+/// What the output is of, ahead of the file records
+#[derive(Serialize)]
+pub struct Manifest<'a> {
+    pub tool: &'static str,
+    pub version: &'static str,
+    /// The root as given; with several, the first
+    pub root: &'a str,
+    /// Every root, when there are several
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roots: Option<Vec<&'a str>>,
+    /// RFC 3339; SOURCE_DATE_EPOCH when set
+    pub generated: String,
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<&'a str>,
+    pub filters: Filters,
+    pub files: usize,
+}
+
+/// One included file
+#[derive(Serialize)]
+struct Record<'a> {
+    path: &'a str,
+    size: u64,
+    lines: usize,
+    language: Option<&'static str>,
+    /// Header notes, e.g. `truncated` or `lines 3-7 of 50`
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    notes: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<&'a str>,
+    content: &'a str,
+}
+
+impl<'a> Record<'a> {
+    fn new(file: &'a TemplateFile) -> Record<'a> {
+        Record {
+            path: &file.path,
+            size: file.size,
+            lines: file.lines,
+            language: file.language,
+            notes: &file.notes,
+            commit: file.commit.as_deref(),
+            content: &file.content,
+        }
+    }
+}
+
+/// A `--format json` output
+#[derive(Serialize)]
+struct Document<'a> {
+    manifest: &'a Manifest<'a>,
+    files: Vec<Record<'a>>,
+}
+
+/// A JSON Lines record, tagged with what it holds
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Line<'a> {
+    Manifest(&'a Manifest<'a>),
+    File(Record<'a>),
+}
+
+/// The whole `--format json` or `--format jsonl` output
+///
+/// JSON is one object holding the manifest and the array of files; JSON
+/// Lines puts the manifest on the first line and each file on one of its
+/// own, so a large output can be read a record at a time.
+pub fn render(format: OutputFormat, manifest: &Manifest, files: &[TemplateFile]) -> Result<String> {
+    if format == OutputFormat::Json {
+        let document = Document {
+            manifest,
+            files: files.iter().map(Record::new).collect(),
+        };
+        return Ok(serde_json::to_string_pretty(&document)? + "\n");
+    }
+    let mut out = serde_json::to_string(&Line::Manifest(manifest))? + "\n";
+    for file in files {
+        out.push_str(&serde_json::to_string(&Line::File(Record::new(file)))?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Whether `format` is one of the JSON ones
+pub fn is_structured(format: OutputFormat) -> bool {
+    matches!(format, OutputFormat::Json | OutputFormat::Jsonl)
+}
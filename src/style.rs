@@ -0,0 +1,62 @@
+use crate::cli::ColorChoice;
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// ANSI styling for the reports printed to stdout
+#[derive(Copy, Clone)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    /// `auto` colors only a terminal, and honors `NO_COLOR`
+    pub fn new(choice: ColorChoice) -> Style {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        };
+        Style { enabled }
+    }
+
+    fn paint(self, code: &str, text: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn included(self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    pub fn excluded(self, text: &str) -> String {
+        self.paint("31", text)
+    }
+
+    pub fn dim(self, text: &str) -> String {
+        self.paint("2", text)
+    }
+
+    pub fn bold(self, text: &str) -> String {
+        self.paint("1", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_on_request() {
+        let always = Style::new(ColorChoice::Always);
+        assert_eq!(always.included("ok"), "\x1b[32mok\x1b[0m");
+        assert_eq!(always.excluded("no"), "\x1b[31mno\x1b[0m");
+        let never = Style::new(ColorChoice::Never);
+        assert_eq!(never.bold("x"), "x");
+        assert_eq!(never.dim("x"), "x");
+    }
+}
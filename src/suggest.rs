@@ -0,0 +1,181 @@
+use crate::cli::Args;
+use crate::config;
+use crate::explain;
+use crate::merge::{IGNORE_FILE, human_size};
+use crate::settings::Settings;
+use anyhow::{Result, anyhow};
+use clap::Parser;
+use rayon::prelude::*;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// Directory names that hold build output, vendored code or other files
+/// nobody writes by hand
+const GENERATED_DIRS: &[&str] = &[
+    ".next",
+    ".nuxt",
+    ".parcel-cache",
+    ".svelte-kit",
+    ".turbo",
+    "bower_components",
+    "build",
+    "coverage",
+    "dist",
+    "gen",
+    "generated",
+    "htmlcov",
+    "out",
+    "site-packages",
+    "target",
+    "vendor",
+];
+
+/// Markers code generators leave near the top of their output
+const GENERATED_MARKERS: &[&str] = &["@generated", "do not edit", "auto-generated", "autogenerated"];
+
+/// Longest line plausible in hand-written text; minified code and data
+/// dumps go far beyond it
+const MAX_LINE: usize = 1000;
+
+/// How much of a file is read to judge it
+const SNIFF: u64 = 1024 * 1024;
+
+/// Why an otherwise merged file is proposed for ignoring
+enum Finding {
+    Generated,
+    Large(u64),
+    /// What makes the text look like data
+    NearBinary(&'static str),
+}
+
+fn inspect(path: &Path, large: u64) -> Option<Finding> {
+    let size = fs::metadata(path).ok()?.len();
+    let mut bytes = Vec::new();
+    File::open(path).ok()?.take(SNIFF).read_to_end(&mut bytes).ok()?;
+
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(2048)]).to_ascii_lowercase();
+    if GENERATED_MARKERS.iter().any(|m| head.contains(m)) {
+        return Some(Finding::Generated);
+    }
+    if size > large {
+        return Some(Finding::Large(size));
+    }
+    if std::str::from_utf8(&bytes).is_err() && size <= SNIFF {
+        return Some(Finding::NearBinary("not valid UTF-8"));
+    }
+    if bytes.split(|&b| b == b'\n').any(|line| line.len() > MAX_LINE) {
+        return Some(Finding::NearBinary("minified or data"));
+    }
+    None
+}
+
+/// `treemerge suggest-ignore`: propose a `.treemergeignore` for `dir` from
+/// what a merge with `options` would pick up, on stdout
+pub fn suggest_ignore(dir: &Path, large: u64, options: &[String]) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow!("no such directory: {}", dir.display()));
+    }
+    let argv = ["treemerge".into(), dir.as_os_str().to_owned()]
+        .into_iter()
+        .chain(options.iter().map(Into::into));
+    let args: Settings = Args::parse_from(config::merge_argv(argv)?).into();
+
+    let entries = explain::candidates(dir, &args)?;
+    let included: Vec<(&str, &Path)> = entries
+        .iter()
+        .filter(|(_, _, d)| d.included)
+        .map(|(rel, path, _)| (rel.as_str(), path.as_path()))
+        .collect();
+
+    // Outermost generated directory holding merged files
+    let mut dirs = BTreeSet::new();
+    for (rel, _) in &included {
+        if let Some((i, _)) = rel
+            .match_indices('/')
+            .find(|(i, _)| GENERATED_DIRS.contains(&rel[..*i].rsplit('/').next().unwrap_or_default()))
+        {
+            dirs.insert(rel[..i].to_string());
+        }
+    }
+    let in_dir = |rel: &str| {
+        dirs.iter()
+            .any(|d| rel.strip_prefix(d.as_str()).is_some_and(|r| r.starts_with('/')))
+    };
+    let findings: Vec<(&str, Finding)> = included
+        .par_iter()
+        .filter(|(rel, _)| !in_dir(rel))
+        .filter_map(|(rel, path)| inspect(path, large).map(|f| (*rel, f)))
+        .collect();
+
+    let covered = included.iter().filter(|(rel, _)| in_dir(rel)).count() + findings.len();
+    println!("# Proposed by treemerge suggest-ignore; review, then save as {}", IGNORE_FILE);
+    println!("# in {} (gitignore syntax)", dir.display());
+    if !dirs.is_empty() {
+        println!("\n# Generated or vendored directories");
+        for d in &dirs {
+            println!("/{}/", d);
+        }
+    }
+    let group = |title: &str, matching: &dyn Fn(&Finding) -> Option<String>| {
+        let mut first = true;
+        for (rel, finding) in &findings {
+            let Some(note) = matching(finding) else {
+                continue;
+            };
+            if first {
+                println!("\n# {}", title);
+                first = false;
+            }
+            if !note.is_empty() {
+                println!("# {}", note);
+            }
+            println!("/{}", rel);
+        }
+    };
+    group("Generated files", &|f| {
+        matches!(f, Finding::Generated).then(String::new)
+    });
+    group(&format!("Files over {}", human_size(large)), &|f| match f {
+        Finding::Large(size) => Some(human_size(*size)),
+        _ => None,
+    });
+    group("Minified or non-UTF-8 text", &|f| match f {
+        Finding::NearBinary(why) => Some(why.to_string()),
+        _ => None,
+    });
+    eprintln!(
+        "{} of {} files that would be merged are covered by the suggestions",
+        covered,
+        included.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn findings() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, body: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, body).unwrap();
+            path
+        };
+        let finding = |path: &Path| match inspect(path, 10_000) {
+            None => "none".to_string(),
+            Some(Finding::Generated) => "generated".to_string(),
+            Some(Finding::Large(size)) => format!("large {}", size),
+            Some(Finding::NearBinary(why)) => why.to_string(),
+        };
+        assert_eq!(finding(&file("a.rs", b"fn main() {}\n")), "none");
+        assert_eq!(finding(&file("b.rs", b"// @generated by a tool\nfn f() {}\n")), "generated");
+        assert_eq!(finding(&file("c.txt", "a\n".repeat(6000).as_bytes())), "large 12000");
+        assert_eq!(finding(&file("d.txt", b"caf\xe9\n")), "not valid UTF-8");
+        assert_eq!(finding(&file("e.js", "x;".repeat(600).as_bytes())), "minified or data");
+        assert!(suggest_ignore(&dir.path().join("missing"), 1000, &[]).is_err());
+    }
+}
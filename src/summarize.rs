@@ -0,0 +1,72 @@
+use anyhow::{Result, anyhow};
+use std::fs::File;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Quote `s` as a single shell word
+pub fn shell_quote(s: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
+/// `sh -c command`, or `cmd /C command` on Windows
+pub fn shell(command: &str) -> Command {
+    let mut c = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    c.arg(command);
+    c
+}
+
+/// Run the shell command `template` on `path` and return its output
+///
+/// `{}` in the template is replaced by the quoted path; the file is also
+/// piped to the command's stdin, so filters that read stdin work as is.
+pub fn summarize(template: &str, path: &Path) -> Result<String> {
+    let command = template.replace("{}", &shell_quote(&path.to_string_lossy()));
+    let output = shell(&command)
+        .stdin(File::open(path)?)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("cannot run summarizer: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "summarizer failed for {} ({})",
+            path.display(),
+            output.status
+        ));
+    }
+    let mut summary = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !summary.ends_with('\n') {
+        summary.push('\n');
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("it's.txt");
+        std::fs::write(&path, "one\ntwo\n").unwrap();
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        // The file is both on stdin and in place of {}
+        assert_eq!(summarize("wc -l", &path).unwrap().trim(), "2");
+        assert_eq!(summarize("head -n 1 {}", &path).unwrap(), "one\n");
+        assert_eq!(summarize("printf done", &path).unwrap(), "done\n");
+        assert!(summarize("exit 3", &path).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::git::RepoInfo;
+use crate::tree;
+use anyhow::{Context, Result};
+use minijinja::{context, Environment};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Per-file values available to templates as items of `files`
+#[derive(Serialize)]
+pub struct TemplateFile {
+    pub path: String,
+    pub language: Option<&'static str>,
+    pub size: u64,
+    pub lines: usize,
+    pub notes: Vec<String>,
+    pub commit: Option<String>,
+    /// Conventional starting point such as `main.rs` or `package.json`
+    pub entry_point: bool,
+    pub content: String,
+}
+
+/// Repository values available to templates as `repo`
+#[derive(Serialize)]
+struct TemplateRepo {
+    remote: Option<String>,
+    branch: Option<String>,
+    commit: Option<String>,
+    commit_date: Option<String>,
+    dirty: bool,
+}
+
+/// Render a user-supplied template over the selected files
+///
+/// The template sees `root`, `version`, `files` (see `TemplateFile`),
+/// `tree` (the selected paths as a directory tree), `total_size`,
+/// `total_lines`, `repo` (unset outside git repositories) and
+/// `instructions` (the `--instructions` text, if any).
+pub fn render(
+    template: &Path,
+    root: &Path,
+    files: &[TemplateFile],
+    repo: Option<RepoInfo>,
+    instructions: Option<&str>,
+) -> Result<String> {
+    let source = fs::read_to_string(template)
+        .with_context(|| format!("cannot read template {}", template.display()))?;
+    render_str(&template.to_string_lossy(), &source, root, files, repo, instructions)
+}
+
+/// Render template source registered under `name`
+pub fn render_str(
+    name: &str,
+    source: &str,
+    root: &Path,
+    files: &[TemplateFile],
+    repo: Option<RepoInfo>,
+    instructions: Option<&str>,
+) -> Result<String> {
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.add_template(name, source)
+        .with_context(|| format!("invalid template {}", name))?;
+
+    let repo = repo.map(|r| TemplateRepo {
+        remote: r.remote,
+        branch: r.branch,
+        commit: r.commit,
+        commit_date: r.commit_date,
+        dirty: r.dirty,
+    });
+
+    let paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+    let ctx = context! {
+        root => root.to_string_lossy(),
+        version => env!("CARGO_PKG_VERSION"),
+        files => files,
+        tree => tree::render(&paths),
+        total_size => files.iter().map(|f| f.size).sum::<u64>(),
+        total_lines => files.iter().map(|f| f.lines).sum::<usize>(),
+        repo => repo,
+        instructions => instructions,
+    };
+
+    env.get_template(name)?
+        .render(ctx)
+        .with_context(|| format!("failed to render template {}", name))
+}
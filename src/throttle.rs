@@ -0,0 +1,105 @@
+use std::io::{self, Read};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Aggregate read rate shared by every thread
+struct Limit {
+    bytes_per_sec: f64,
+    /// When the bytes read so far are paid for
+    next: Mutex<Instant>,
+}
+
+static LIMIT: OnceLock<Limit> = OnceLock::new();
+
+/// Cap input reads at `bytes_per_sec` for the rest of the run
+pub fn init(bytes_per_sec: f64) {
+    let _ = LIMIT.set(Limit {
+        bytes_per_sec,
+        next: Mutex::new(Instant::now()),
+    });
+}
+
+/// Account for `n` bytes just read, sleeping once the rate is exceeded
+fn consume(n: usize) {
+    let Some(limit) = LIMIT.get() else {
+        return;
+    };
+    let cost = Duration::from_secs_f64(n as f64 / limit.bytes_per_sec);
+    let wait = {
+        let mut next = limit.next.lock().unwrap();
+        let now = Instant::now();
+        // Idle time earns no credit, so reads never come in bursts
+        *next = (*next).max(now) + cost;
+        *next - now
+    };
+    if !wait.is_zero() {
+        thread::sleep(wait);
+    }
+}
+
+/// Reader counting against `--io-limit`
+pub struct Throttled<R>(R);
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.read(buf)?;
+        consume(n);
+        Ok(n)
+    }
+}
+
+pub fn reader<R: Read>(inner: R) -> Throttled<R> {
+    Throttled(inner)
+}
+
+/// Lower this process's I/O (and CPU) priority, for `--nice-io`
+///
+/// Returns false where the platform offers no way to.
+pub fn lower_priority() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        // IOPRIO_CLASS_IDLE would need the raw syscall; with no explicit
+        // I/O class the kernel derives it from the nice value
+        unsafe extern "C" {
+            fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+        }
+        // SAFETY: PRIO_PROCESS (0) for the calling process (0)
+        unsafe { setpriority(0, 0, 19) == 0 }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        unsafe extern "C" {
+            fn setiopolicy_np(iotype: i32, scope: i32, policy: i32) -> i32;
+        }
+        // SAFETY: IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE
+        unsafe { setiopolicy_np(0, 0, 3) == 0 }
+    }
+    #[cfg(windows)]
+    {
+        unsafe extern "system" {
+            fn GetCurrentProcess() -> isize;
+            fn SetPriorityClass(process: isize, class: u32) -> i32;
+        }
+        const PROCESS_MODE_BACKGROUND_BEGIN: u32 = 0x0010_0000;
+        // SAFETY: the pseudo-handle of the current process needs no closing
+        unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) != 0 }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_reads_pass_through() {
+        // No --io-limit: nothing to wait for
+        let mut text = String::new();
+        reader(&b"some bytes"[..]).read_to_string(&mut text).unwrap();
+        assert_eq!(text, "some bytes");
+    }
+}
@@ -0,0 +1,456 @@
+use crate::merge::compile_globs;
+use crate::pii;
+use anyhow::{Context, Result, anyhow};
+use globset::GlobSet;
+use serde::Deserialize;
+
+/// Content transforms the `[[transforms]]` config pipeline can run
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+    /// Remove comments, for languages whose comment syntax is known
+    StripComments,
+    /// Collapse runs of blank lines into one
+    SqueezeBlank,
+    /// Mask emails, phone numbers and IP addresses (as `--redact-pii`)
+    RedactPii,
+    /// Keep the first `lines` lines
+    Truncate,
+}
+
+impl Step {
+    pub const ALL: [Step; 4] = [Step::StripComments, Step::SqueezeBlank, Step::RedactPii, Step::Truncate];
+
+    /// Header note of a file the step changed; `redact-pii` puts the
+    /// number of replacements before it
+    pub fn note(self) -> &'static str {
+        match self {
+            Step::StripComments => "comments stripped",
+            Step::SqueezeBlank => "blank lines squeezed",
+            Step::RedactPii => "PII redacted",
+            Step::Truncate => "truncated",
+        }
+    }
+}
+
+/// One step of the pipeline, as written in the config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transform {
+    pub step: Step,
+    /// Root-relative globs the step is limited to (default: every file)
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Lines kept by `truncate`
+    pub lines: Option<usize>,
+}
+
+impl Transform {
+    /// `step` applied to every file
+    pub fn everywhere(step: Step) -> Transform {
+        Transform {
+            step,
+            paths: Vec::new(),
+            lines: None,
+        }
+    }
+}
+
+/// Treatment of the files matching one glob, from a `[[rules]]` table
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Root-relative glob, e.g. `vendor/**`
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default)]
+    pub strip_comments: bool,
+    #[serde(default)]
+    pub squeeze_blank: bool,
+    #[serde(default)]
+    pub redact_pii: bool,
+    /// Lines kept of each matching file
+    pub max_lines: Option<usize>,
+}
+
+impl Rule {
+    /// The rule as pipeline steps, in pipeline order
+    pub fn transforms(&self) -> Vec<Transform> {
+        let scoped = |step, lines| Transform {
+            step,
+            paths: vec![self.pattern.clone()],
+            lines,
+        };
+        let mut steps = Vec::new();
+        if self.strip_comments {
+            steps.push(scoped(Step::StripComments, None));
+        }
+        if self.squeeze_blank {
+            steps.push(scoped(Step::SqueezeBlank, None));
+        }
+        if self.redact_pii {
+            steps.push(scoped(Step::RedactPii, None));
+        }
+        if let Some(lines) = self.max_lines {
+            steps.push(scoped(Step::Truncate, Some(lines)));
+        }
+        steps
+    }
+}
+
+/// The configured transforms, checked and with their globs compiled
+pub struct Pipeline {
+    steps: Vec<(Transform, Option<GlobSet>)>,
+}
+
+/// Result of running the pipeline over one file
+pub struct Applied {
+    pub text: String,
+    /// What changed, for the file's header
+    pub notes: Vec<String>,
+    /// Replacements made by `redact-pii`
+    pub redacted: usize,
+}
+
+impl Pipeline {
+    pub fn new(transforms: &[Transform]) -> Result<Pipeline> {
+        let mut steps = Vec::new();
+        for (index, t) in transforms.iter().enumerate() {
+            let which = || format!("transforms[{}]", index);
+            match (t.step, t.lines) {
+                (Step::Truncate, None) => return Err(anyhow!("{}: truncate needs `lines`", which())),
+                (Step::Truncate, Some(_)) | (_, None) => {}
+                (_, Some(_)) => return Err(anyhow!("{}: only truncate takes `lines`", which())),
+            }
+            let globs = if t.paths.is_empty() {
+                None
+            } else {
+                Some(compile_globs(&t.paths).with_context(which)?)
+            };
+            steps.push((t.clone(), globs));
+        }
+        Ok(Pipeline { steps })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Run every step whose paths match `rel` over `text`, in order
+    ///
+    /// `rel` is None for generated sections, which only scoped-less steps
+    /// reach; `file` is false for text that is not a file's own content
+    /// (diffs, outlines, summaries, command output), which only `redact-pii`
+    /// touches.
+    pub fn apply(&self, rel: Option<&str>, lang: Option<&str>, file: bool, text: &str) -> Applied {
+        let mut applied = Applied {
+            text: text.to_string(),
+            notes: Vec::new(),
+            redacted: 0,
+        };
+        for (t, globs) in &self.steps {
+            let in_scope = match (globs, rel) {
+                (None, _) => true,
+                (Some(globs), Some(rel)) => globs.is_match(rel),
+                (Some(_), None) => false,
+            };
+            if !in_scope || (t.step != Step::RedactPii && !file) {
+                continue;
+            }
+            match t.step {
+                Step::StripComments => {
+                    let stripped = strip_comments(lang, &applied.text);
+                    if stripped != applied.text {
+                        applied.text = stripped;
+                        applied.notes.push(t.step.note().into());
+                    }
+                }
+                Step::SqueezeBlank => {
+                    let squeezed = squeeze_blank(&applied.text);
+                    if squeezed != applied.text {
+                        applied.text = squeezed;
+                        applied.notes.push(t.step.note().into());
+                    }
+                }
+                Step::RedactPii => {
+                    let (text, n) = pii::redact(&applied.text);
+                    applied.text = text;
+                    applied.redacted += n;
+                }
+                Step::Truncate => {
+                    if let Some(text) = truncate(&applied.text, t.lines.unwrap_or(0)) {
+                        applied.text = text;
+                        applied.notes.push(t.step.note().into());
+                    }
+                }
+            }
+        }
+        if applied.redacted > 0 {
+            applied.notes.push(format!("{} {}", applied.redacted, Step::RedactPii.note()));
+        }
+        applied
+    }
+}
+
+/// Comment syntax of a language
+struct Syntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    /// Characters opening a string that runs to the same character
+    quotes: &'static [char],
+}
+
+fn syntax(lang: &str) -> Option<Syntax> {
+    const C_BLOCK: Option<(&str, &str)> = Some(("/*", "*/"));
+    let s = |line, block, quotes| Some(Syntax { line, block, quotes });
+    match lang {
+        // Single quotes are lifetimes as often as char literals
+        "rust" => s(&["//"], C_BLOCK, &['"']),
+        "c" | "cpp" | "java" | "kotlin" | "scala" | "swift" | "csharp" | "protobuf" => {
+            s(&["//"], C_BLOCK, &['"', '\''])
+        }
+        "go" | "javascript" | "typescript" => s(&["//"], C_BLOCK, &['"', '\'', '`']),
+        "php" => s(&["//", "#"], C_BLOCK, &['"', '\'']),
+        "css" => s(&[], C_BLOCK, &['"', '\'']),
+        "scss" => s(&["//"], C_BLOCK, &['"', '\'']),
+        "python" | "ruby" | "perl" | "r" | "julia" | "elixir" | "powershell" | "toml" | "yaml" => {
+            s(&["#"], None, &['"', '\''])
+        }
+        "bash" => s(&["#"], None, &['"', '\'', '`']),
+        "nix" | "make" | "cmake" | "dockerfile" => s(&["#"], None, &['"']),
+        "ini" => s(&["#", ";"], None, &['"']),
+        "sql" => s(&["--"], C_BLOCK, &['"', '\'']),
+        "lua" => s(&["--"], None, &['"', '\'']),
+        "haskell" => s(&["--"], Some(("{-", "-}")), &['"']),
+        "ocaml" => s(&[], Some(("(*", "*)")), &['"']),
+        "erlang" | "latex" => s(&["%"], None, &['"']),
+        "html" | "xml" | "markdown" | "vue" | "svelte" => s(&[], Some(("<!--", "-->")), &[]),
+        _ => None,
+    }
+}
+
+/// Whether a line comment `marker` may start after `prev`: `#`, `;` and `%`
+/// are too common inside tokens (`$#`, `a;b`, `\%`) to count anywhere
+fn starts_comment(marker: &str, prev: Option<char>) -> bool {
+    match marker {
+        "#" | ";" => prev.is_none_or(char::is_whitespace),
+        "%" => prev != Some('\\'),
+        _ => true,
+    }
+}
+
+/// Remove comments from `text`; text of other languages is returned as is
+///
+/// The scan is lexical and shallow: strings are tracked within a line, so a
+/// marker inside quotes survives, and lines left empty by the removal (or
+/// inside a block comment) are dropped. A shebang line is kept.
+pub fn strip_comments(lang: Option<&str>, text: &str) -> String {
+    let Some(syntax) = lang.and_then(syntax) else {
+        return text.to_string();
+    };
+    let rust = lang == Some("rust");
+    let mut out = String::with_capacity(text.len());
+    let mut in_block = false;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let eol = &line[body.len()..];
+        if index == 0 && body.starts_with("#!") {
+            out.push_str(line);
+            continue;
+        }
+
+        let inside = in_block;
+        let mut kept = String::with_capacity(body.len());
+        let mut quote = None;
+        let mut prev = None;
+        let mut rest = body;
+        while let Some(c) = rest.chars().next() {
+            if in_block {
+                let (_, end) = syntax.block.unwrap_or_default();
+                match rest.strip_prefix(end) {
+                    Some(after) => {
+                        in_block = false;
+                        rest = after;
+                        // Keep `a/* x */b` two tokens
+                        let joins = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                        if joins(kept.chars().last()) && joins(rest.chars().next()) {
+                            kept.push(' ');
+                        }
+                    }
+                    None => rest = &rest[c.len_utf8()..],
+                }
+                continue;
+            }
+            if let Some(q) = quote {
+                kept.push(c);
+                rest = &rest[c.len_utf8()..];
+                if c == '\\' {
+                    if let Some(next) = rest.chars().next() {
+                        kept.push(next);
+                        rest = &rest[next.len_utf8()..];
+                    }
+                } else if c == q {
+                    quote = None;
+                }
+                prev = Some(c);
+                continue;
+            }
+            if let Some((start, _)) = syntax.block
+                && let Some(after) = rest.strip_prefix(start)
+            {
+                in_block = true;
+                rest = after;
+                continue;
+            }
+            if syntax.line.iter().any(|m| rest.starts_with(m) && starts_comment(m, prev)) {
+                break;
+            }
+            if rust && c == '\'' {
+                // A char literal such as '"' or '\'' is copied whole
+                let len = match rest[1..].chars().next() {
+                    Some('\\') => rest.get(3..).and_then(|r| r.find('\'')).map(|i| i + 4),
+                    Some(ch) if rest[1 + ch.len_utf8()..].starts_with('\'') => Some(2 + ch.len_utf8()),
+                    _ => None,
+                };
+                if let Some(len) = len.filter(|&len| len <= rest.len()) {
+                    kept.push_str(&rest[..len]);
+                    rest = &rest[len..];
+                    prev = Some('\'');
+                    continue;
+                }
+            }
+            if syntax.quotes.contains(&c) {
+                quote = Some(c);
+            }
+            kept.push(c);
+            prev = Some(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        if kept.len() == body.len() && !inside {
+            out.push_str(line);
+            continue;
+        }
+        let kept = kept.trim_end();
+        if kept.trim_start().is_empty() && (inside || !body.trim().is_empty()) {
+            continue;
+        }
+        out.push_str(kept);
+        out.push_str(eol);
+    }
+    out
+}
+
+/// Collapse every run of blank (or whitespace-only) lines into one empty line
+pub fn squeeze_blank(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+        } else {
+            out.push_str(line);
+            blank = false;
+        }
+    }
+    out
+}
+
+/// The first `lines` lines of `text` with a note of how many were kept, or
+/// None when it is no longer than that
+pub fn truncate(text: &str, lines: usize) -> Option<String> {
+    let total = text.lines().count();
+    if total <= lines {
+        return None;
+    }
+    let mut out: String = text.split_inclusive('\n').take(lines).collect();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&format!("({} of {} lines shown)\n", lines, total));
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_go_and_strings_stay() {
+        let rust = "// header\nlet s = \"// not a comment\"; // gone\nlet c = '\"'; /* a\nb */ let d = 1;\nfn f<'a>(x: &'a str) {} // x\n";
+        assert_eq!(
+            strip_comments(Some("rust"), rust),
+            "let s = \"// not a comment\";\nlet c = '\"';\n let d = 1;\nfn f<'a>(x: &'a str) {}\n"
+        );
+        assert_eq!(strip_comments(Some("c"), "a/* x */b\r\n"), "a b\r\n");
+        assert_eq!(strip_comments(Some("python"), "#!/usr/bin/env python\n# doc\nx = '#1'  # n\n\n"), "#!/usr/bin/env python\nx = '#1'\n\n");
+        assert_eq!(strip_comments(Some("bash"), "echo $# ${#a}\n"), "echo $# ${#a}\n");
+        assert_eq!(strip_comments(Some("latex"), "50\\% off % note\n"), "50\\% off\n");
+        assert_eq!(strip_comments(Some("html"), "<p><!-- x --></p>\n"), "<p></p>\n");
+        assert_eq!(strip_comments(None, "// kept\n"), "// kept\n");
+        assert_eq!(strip_comments(Some("rust"), "/* never closed\nstill\n"), "");
+    }
+
+    #[test]
+    fn squeeze_and_truncate() {
+        assert_eq!(squeeze_blank("a\n\n \n\t\nb\n"), "a\n\nb\n");
+        assert_eq!(truncate("1\n2\n3", 2).as_deref(), Some("1\n2\n(2 of 3 lines shown)\n"));
+        assert_eq!(truncate("1\n2\n", 2), None);
+        let pipeline = Pipeline::new(&[Transform::everywhere(Step::Truncate)]);
+        assert!(pipeline.is_err());
+    }
+
+    #[test]
+    fn pipelines_check_and_scope_their_steps() {
+        let parse = |text: &str| toml::from_str::<Transform>(text).unwrap();
+        assert!(Pipeline::new(&[parse("step = \"truncate\"")]).is_err());
+        assert!(Pipeline::new(&[parse("step = \"squeeze-blank\"\nlines = 3")]).is_err());
+        assert!(Pipeline::new(&[parse("step = \"squeeze-blank\"\npaths = [\"[\"]")]).is_err());
+        assert!(toml::from_str::<Transform>("step = \"minify\"").is_err());
+
+        let pipeline = Pipeline::new(&[
+            parse("step = \"strip-comments\"\npaths = [\"src/**\"]"),
+            parse("step = \"truncate\"\nlines = 1"),
+        ])
+        .unwrap();
+        let applied = pipeline.apply(Some("src/a.rs"), Some("rust"), true, "// doc\nfn a() {}\nfn b() {}\n");
+        assert_eq!(applied.text, "fn a() {}\n(1 of 2 lines shown)\n");
+        assert_eq!(applied.notes, ["comments stripped", "truncated"]);
+        let applied = pipeline.apply(Some("docs/a.rs"), Some("rust"), true, "// doc\nfn a() {}\n");
+        assert!(applied.text.starts_with("// doc\n"));
+        let squeeze = Pipeline::new(&[parse("step = \"squeeze-blank\"")]).unwrap();
+        assert_eq!(squeeze.apply(Some("a.txt"), None, true, "a\n\n\nb\n").notes, ["blank lines squeezed"]);
+        assert!(squeeze.apply(Some("a.txt"), None, true, "a\n\nb\n").notes.is_empty());
+        // Generated text only meets unscoped redaction
+        let applied = pipeline.apply(None, None, false, "one\ntwo\n");
+        assert_eq!((applied.text.as_str(), applied.notes.len()), ("one\ntwo\n", 0));
+    }
+
+    #[test]
+    fn rules_become_scoped_steps() {
+        let rule: Rule = toml::from_str("match = \"vendor/**\"\nmax_lines = 2\nstrip_comments = true").unwrap();
+        let steps = rule.transforms();
+        assert_eq!(steps.iter().map(|t| t.step).collect::<Vec<_>>(), [Step::StripComments, Step::Truncate]);
+        assert!(steps.iter().all(|t| t.paths == ["vendor/**"]));
+        assert_eq!(steps[1].lines, Some(2));
+        assert!(toml::from_str::<Rule>("max_lines = 2").is_err());
+        assert!(toml::from_str::<Rule>("match = \"*\"\nsqueeze = true").is_err());
+    }
+
+    #[test]
+    fn redact_pii_steps_and_rules() {
+        let rule: Rule = toml::from_str("match = \"logs/**\"\nredact_pii = true").unwrap();
+        let pipeline = Pipeline::new(&rule.transforms()).unwrap();
+        let applied = pipeline.apply(Some("logs/a.txt"), None, true, "from jo@example.org\n");
+        assert_eq!(applied.text, "from [email redacted]\n");
+        assert_eq!(applied.redacted, 1);
+        let untouched = pipeline.apply(Some("src/a.rs"), None, true, "jo@example.org\n");
+        assert_eq!(untouched.redacted, 0);
+        // `redact` alone is the secrets option, not a step or a rule key
+        assert!(toml::from_str::<Transform>("step = \"redact\"").is_err());
+        assert!(toml::from_str::<Rule>("match = \"*\"\nredact = true").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::priority;
+use std::collections::BTreeMap;
+
+/// Nested directory listing built from `/`- (or natively) separated paths
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    /// Shown after a file's name
+    note: String,
+}
+
+/// Render paths as an indented tree, directories before files at each level
+///
+/// Entry points (see `priority::is_entry_point`) are flagged.
+///
+/// ```text
+/// .
+/// ├── src/
+/// │   ├── cli.rs
+/// │   └── main.rs  [entry point]
+/// └── Cargo.toml  [entry point]
+/// ```
+pub fn render(paths: &[String]) -> String {
+    let entries: Vec<(String, String)> = paths.iter().map(|p| (p.clone(), String::new())).collect();
+    render_notes(&entries)
+}
+
+/// Like [`render`], with each file's note (when not empty) in parentheses
+/// after its name
+pub fn render_notes(entries: &[(String, String)]) -> String {
+    let mut root = Node::default();
+    for (path, note) in entries {
+        let mut node = &mut root;
+        for part in path
+            .split(['/', std::path::MAIN_SEPARATOR])
+            .filter(|p| !p.is_empty())
+        {
+            node = node.children.entry(part.to_owned()).or_default();
+        }
+        node.note = note.clone();
+    }
+
+    let mut out = String::from(".\n");
+    render_children(&root, "", &mut out);
+    out
+}
+
+fn render_children(node: &Node, prefix: &str, out: &mut String) {
+    let (dirs, files): (Vec<_>, Vec<_>) = node
+        .children
+        .iter()
+        .partition(|(_, n)| !n.children.is_empty());
+    let entries: Vec<_> = dirs.into_iter().chain(files).collect();
+    for (idx, (name, child)) in entries.iter().enumerate() {
+        let last = idx + 1 == entries.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        if child.children.is_empty() {
+            let flag = if priority::is_entry_point(name) {
+                "  [entry point]"
+            } else {
+                ""
+            };
+            let note = if child.note.is_empty() {
+                String::new()
+            } else {
+                format!("  ({})", child.note)
+            };
+            out.push_str(&format!("{}{}{}{}{}\n", prefix, branch, name, note, flag));
+        } else {
+            out.push_str(&format!("{}{}{}/\n", prefix, branch, name));
+            render_children(child, &format!("{}{}", prefix, indent), out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directories_first() {
+        let paths = ["Cargo.toml", "src/main.rs", "src/cli.rs"].map(String::from);
+        assert_eq!(
+            render(&paths),
+            ".\n├── src/\n│   ├── cli.rs\n│   └── main.rs  [entry point]\n└── Cargo.toml  [entry point]\n"
+        );
+        assert_eq!(render(&[]), ".\n");
+    }
+
+    #[test]
+    fn notes_after_names() {
+        let entries = [("src/main.rs", "3 lines"), ("src/util.rs", ""), ("a.txt", "1 line")]
+            .map(|(p, n)| (p.to_string(), n.to_string()));
+        assert_eq!(
+            render_notes(&entries),
+            ".\n├── src/\n│   ├── main.rs  (3 lines)  [entry point]\n│   └── util.rs\n└── a.txt  (1 line)\n"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Result};
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Owner and permission-bit filters for shared machines
+pub struct OwnershipFilter {
+    uid: Option<u32>,
+    mask: Option<u32>,
+}
+
+/// Resolve a user name (or numeric uid) to a uid
+fn lookup_uid(user: &str) -> Result<u32> {
+    if let Ok(uid) = user.parse() {
+        return Ok(uid);
+    }
+    let name = CString::new(user).map_err(|_| anyhow!("invalid user name: {}", user))?;
+    // SAFETY: getpwnam returns NULL or a pointer to a static passwd record,
+    // which is read immediately and not retained.
+    let pw = unsafe { libc::getpwnam(name.as_ptr()) };
+    if pw.is_null() {
+        return Err(anyhow!("unknown user: {}", user));
+    }
+    // SAFETY: non-null result from getpwnam, see above
+    let pw = unsafe { &*pw };
+    Ok(pw.pw_uid)
+}
+
+impl OwnershipFilter {
+    pub fn new(owned_by: Option<&str>, mask: Option<u32>) -> Result<OwnershipFilter> {
+        Ok(OwnershipFilter {
+            uid: owned_by.map(lookup_uid).transpose()?,
+            mask,
+        })
+    }
+
+    /// Whether `path` satisfies the owner and permission requirements
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.uid.is_none() && self.mask.is_none() {
+            return true;
+        }
+        let Ok(meta) = fs::metadata(path) else {
+            return false;
+        };
+        if self.uid.is_some_and(|uid| meta.uid() != uid) {
+            return false;
+        }
+        if let Some(mask) = self.mask
+            && meta.mode() & mask != mask
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Raw `listxattr`/`getxattr`, whose signatures differ between Linux and macOS
+///
+/// Both return the size needed when `buf` is empty.
+fn list_raw(path: &CString, buf: &mut [u8]) -> isize {
+    let ptr = buf.as_mut_ptr().cast();
+    // SAFETY: `ptr` is valid for `buf.len()` bytes (or unused when 0)
+    #[cfg(target_os = "macos")]
+    let n = unsafe { libc::listxattr(path.as_ptr(), ptr, buf.len(), 0) };
+    #[cfg(not(target_os = "macos"))]
+    let n = unsafe { libc::listxattr(path.as_ptr(), ptr, buf.len()) };
+    n as isize
+}
+
+fn get_raw(path: &CString, name: &CString, buf: &mut [u8]) -> isize {
+    let ptr = buf.as_mut_ptr().cast();
+    // SAFETY: as in `list_raw`
+    #[cfg(target_os = "macos")]
+    let n = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), ptr, buf.len(), 0, 0) };
+    #[cfg(not(target_os = "macos"))]
+    let n = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), ptr, buf.len()) };
+    n as isize
+}
+
+/// Call `raw` with a buffer of the size it asks for; the attributes can
+/// change in between, so retry until the answer fits
+fn sized(mut raw: impl FnMut(&mut [u8]) -> isize) -> std::io::Result<Vec<u8>> {
+    loop {
+        let needed = raw(&mut []);
+        if needed < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut buf = vec![0u8; needed as usize];
+        let n = raw(&mut buf);
+        if n >= 0 {
+            buf.truncate(n as usize);
+            return Ok(buf);
+        }
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ERANGE) {
+            return Err(err);
+        }
+    }
+}
+
+/// Which extended attributes go into the manifest: those matching one of
+/// the `--xattrs` globs (such as `user.*`)
+pub struct XattrFilter(globset::GlobSet);
+
+impl XattrFilter {
+    pub fn new(patterns: &[String]) -> Result<XattrFilter> {
+        let mut builder = globset::GlobSetBuilder::new();
+        for p in patterns {
+            builder.add(globset::Glob::new(p).map_err(|e| anyhow!("invalid --xattrs pattern: {}", e))?);
+        }
+        Ok(XattrFilter(builder.build()?))
+    }
+
+    /// Matching attributes of `path`; values that are not UTF-8 are written
+    /// as `hex:` followed by their bytes in hex
+    pub fn read(&self, path: &Path) -> Result<std::collections::BTreeMap<String, String>> {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let names = match sized(|buf| list_raw(&c_path, buf)) {
+            Ok(names) => names,
+            // Filesystems without xattr support have none to record
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => Vec::new(),
+            Err(e) => return Err(anyhow!("cannot list xattrs of {}: {}", path.display(), e)),
+        };
+
+        let mut out = std::collections::BTreeMap::new();
+        for name in names.split(|&b| b == 0).filter(|n| !n.is_empty()) {
+            let name = String::from_utf8_lossy(name).into_owned();
+            if !self.0.is_match(&name) {
+                continue;
+            }
+            let c_name = CString::new(name.as_bytes())?;
+            let value = sized(|buf| get_raw(&c_path, &c_name, buf))
+                .map_err(|e| anyhow!("cannot read xattr {} of {}: {}", name, path.display(), e))?;
+            let value = match String::from_utf8(value) {
+                Ok(text) => text,
+                Err(e) => {
+                    let hex: String = e.as_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+                    format!("hex:{}", hex)
+                }
+            };
+            out.insert(name, value);
+        }
+        Ok(out)
+    }
+}
+
+/// Owner, group and hard link count, as `uid:gid` and a number
+pub fn ownership(meta: &fs::Metadata) -> (String, u64) {
+    (format!("{}:{}", meta.uid(), meta.gid()), meta.nlink())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn users_by_name_or_uid() {
+        assert_eq!(lookup_uid("0").unwrap(), 0);
+        assert_eq!(lookup_uid("root").unwrap(), 0);
+        assert!(lookup_uid("no-such-user-here").is_err());
+    }
+
+    #[test]
+    fn permission_masks() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "a").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let uid = fs::metadata(&path).unwrap().uid();
+
+        assert!(OwnershipFilter::new(None, None).unwrap().matches(&path));
+        assert!(OwnershipFilter::new(None, Some(0o040)).unwrap().matches(&path));
+        assert!(!OwnershipFilter::new(None, Some(0o004)).unwrap().matches(&path));
+        assert!(OwnershipFilter::new(Some(&uid.to_string()), Some(0o600)).unwrap().matches(&path));
+        assert!(!OwnershipFilter::new(Some(&(uid + 1).to_string()), None).unwrap().matches(&path));
+    }
+
+    #[test]
+    fn xattrs_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "a").unwrap();
+        fs::hard_link(&path, dir.path().join("b.txt")).unwrap();
+        let meta = fs::metadata(&path).unwrap();
+        assert_eq!(ownership(&meta), (format!("{}:{}", meta.uid(), meta.gid()), 2));
+
+        assert!(XattrFilter::new(&["user.[".into()]).is_err());
+        let filter = XattrFilter::new(&["user.treemerge-test.*".into()]).unwrap();
+        assert!(filter.read(&path).unwrap().is_empty());
+        assert!(filter.read(&dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::cli::OutputFormat;
+use crate::manifest;
+use crate::previous::{self, Entry};
+use crate::restore::{relative, resolve};
+use crate::transform::Step;
+use anyhow::{Context, Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Labels of the sections a merge generates rather than reads from a file
+const GENERATED: &[&str] = &[
+    "Instructions",
+    "Contents",
+    "Repository",
+    "File tree",
+    "Dependencies",
+    "Dependency graph",
+    "Per-file statistics",
+];
+
+/// Why an entry holds no file content to restore, if it does not
+fn not_a_file(entry: &Entry) -> Option<&'static str> {
+    if GENERATED.contains(&entry.path.as_str()) || entry.path.starts_with("$ ") {
+        return Some("generated section");
+    }
+    entry.notes.iter().find_map(|note| match note.as_str() {
+        "command output" | "repository" => Some("generated section"),
+        "directory" => Some("directory summary"),
+        "symlink, not followed" => Some("symlink stub"),
+        n if n.starts_with("git-lfs pointer") => Some("git-lfs pointer"),
+        n if n.starts_with("diff ") => Some("diff"),
+        _ => None,
+    })
+}
+
+/// Whether the body is something other than the file's full content: a
+/// transform step changed it, or it was outlined, summarized, redacted or
+/// cut to a line range
+fn lossy(entry: &Entry) -> bool {
+    entry.notes.iter().any(|note| {
+        Step::ALL.iter().any(|step| note.ends_with(step.note()))
+            || matches!(note.as_str(), "outline" | "summary")
+            || note.ends_with(" redacted")
+            || note.starts_with("lines ")
+    })
+}
+
+/// Recreate the files of the merge output `file` (and its split parts) under
+/// `out_dir`, with the modes and mtimes of `manifest` when one is given
+pub fn unmerge(file: &Path, out_dir: &Path, force: bool, manifest: Option<&Path>) -> Result<()> {
+    let Some(texts) = previous::read(file)? else {
+        return Err(anyhow!("{}: no such file", file.display()));
+    };
+    let mut files = Vec::new();
+    let mut markdown = false;
+    for text in &texts {
+        let (format, style) = previous::detect(text);
+        markdown |= format == OutputFormat::Markdown;
+        files.extend(previous::parse(text, format, style));
+    }
+    if markdown {
+        eprintln!("warning: {} is markdown: files that had no final newline get one", file.display());
+    }
+
+    let mut restore = Vec::new();
+    for entry in files {
+        if let Some(why) = not_a_file(&entry) {
+            eprintln!("skipping {}: {}", entry.path, why);
+            continue;
+        }
+        let Some(path) = relative(&entry.path) else {
+            eprintln!("skipping {}: not a relative path", entry.path);
+            continue;
+        };
+        if lossy(&entry) {
+            eprintln!("warning: {} is not restored in full ({})", entry.path, entry.notes.join(", "));
+        }
+        restore.push((entry.path, path, entry.body));
+    }
+    if restore.is_empty() {
+        return Err(anyhow!("{}: no files found in it", file.display()));
+    }
+    let recorded: Option<HashMap<String, manifest::Recorded>> = match manifest {
+        Some(m) => Some(manifest::read(m)?.into_iter().map(|r| (r.path.clone(), r)).collect()),
+        None => None,
+    };
+
+    fs::create_dir_all(out_dir).with_context(|| format!("cannot create {}", out_dir.display()))?;
+    let root = fs::canonicalize(out_dir).with_context(|| format!("cannot read {}", out_dir.display()))?;
+    // Checked for every file before any is written
+    let mut targets = Vec::new();
+    for (_, rel, _) in &restore {
+        targets.push(resolve(&root, rel, false).context("refusing to write through a symlink")?);
+    }
+    if !force {
+        let existing: Vec<String> = restore
+            .iter()
+            .zip(&targets)
+            .filter(|(_, path)| path.exists())
+            .map(|((_, rel, _), _)| out_dir.join(rel).display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(anyhow!(
+                "refusing to overwrite {} (use --force):\n  {}",
+                if existing.len() == 1 { "a file".to_string() } else { format!("{} files", existing.len()) },
+                existing.join("\n  ")
+            ));
+        }
+    }
+    for (shown, rel, body) in &restore {
+        let path = resolve(&root, rel, true).context("refusing to write through a symlink")?;
+        fs::write(&path, body).with_context(|| format!("cannot write {}", path.display()))?;
+        let Some(recorded) = &recorded else {
+            continue;
+        };
+        // Only a file as the manifest saw it gets its metadata back
+        match recorded.get(shown) {
+            Some(r) if r.sha256 == format!("{:x}", Sha256::digest(body.as_bytes())) => {
+                r.apply(&path).with_context(|| format!("cannot set the mode or mtime of {}", path.display()))?;
+            }
+            Some(_) => eprintln!("warning: {} differs from the manifest; mode and mtime not restored", shown),
+            None => eprintln!("warning: {} is not in the manifest; mode and mtime not restored", shown),
+        }
+    }
+    if recorded.is_none() {
+        eprintln!(
+            "restored {} files into {} (modes and mtimes are not restored without --manifest)",
+            restore.len(),
+            out_dir.display()
+        );
+    } else {
+        eprintln!("restored {} files into {}", restore.len(), out_dir.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A text output of `files`, as a merge with the hash headers writes it
+    fn merged(dir: &Path, files: &[(&str, &str)]) -> PathBuf {
+        let mut text = String::from("@treemerge-output 0.0.5 delimiters=safe\n");
+        for (path, body) in files {
+            text.push_str(&format!("\n########## {}\n\n{}", path, body));
+        }
+        let file = dir.join("merged.txt");
+        fs::write(&file, text).unwrap();
+        file
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = merged(dir.path(), &[("a.txt", "a\n"), ("src/lib.rs", "fn f() {}\n\\########## x\n")]);
+        let out = dir.path().join("out");
+        unmerge(&file, &out, false, None).unwrap();
+        assert_eq!(fs::read_to_string(out.join("a.txt")).unwrap(), "a\n");
+        assert_eq!(fs::read_to_string(out.join("src/lib.rs")).unwrap(), "fn f() {}\n########## x\n");
+        assert!(unmerge(&file, &out, false, None).is_err(), "overwrote without --force");
+        unmerge(&file, &out, true, None).unwrap();
+    }
+
+    #[test]
+    fn paths_leaving_the_directory_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside.txt");
+        let absolute = outside.to_string_lossy().into_owned();
+        let file = merged(
+            dir.path(),
+            &[("../outside.txt", "x\n"), ("sub/../../outside.txt", "x\n"), (&absolute, "x\n"), ("ok.txt", "ok\n")],
+        );
+        let out = dir.path().join("out");
+        unmerge(&file, &out, false, None).unwrap();
+        assert!(!outside.exists());
+        assert!(out.join("ok.txt").is_file());
+        assert_eq!(fs::read_dir(&out).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn nothing_only_hostile_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = merged(dir.path(), &[("../x.txt", "x\n"), ("/etc/x.txt", "x\n")]);
+        assert!(unmerge(&file, &dir.path().join("out"), false, None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_under_the_directory_are_not_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let victim = dir.path().join("victim");
+        let out = dir.path().join("out");
+        fs::create_dir_all(&victim).unwrap();
+        fs::create_dir_all(&out).unwrap();
+        std::os::unix::fs::symlink(&victim, out.join("src")).unwrap();
+        std::os::unix::fs::symlink(victim.join("file.txt"), out.join("file.txt")).unwrap();
+
+        let file = merged(dir.path(), &[("src/evil.txt", "x\n")]);
+        assert!(unmerge(&file, &out, true, None).is_err());
+        let file = merged(dir.path(), &[("file.txt", "x\n")]);
+        assert!(unmerge(&file, &out, true, None).is_err());
+        assert_eq!(fs::read_dir(&victim).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn files_in_the_way_of_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("a"), "a file").unwrap();
+        let file = merged(dir.path(), &[("a/b.txt", "x\n")]);
+        assert!(unmerge(&file, &out, true, None).is_err());
+        assert_eq!(fs::read_to_string(out.join("a")).unwrap(), "a file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn manifests_give_back_modes_and_mtimes() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let file = merged(dir.path(), &[("run.sh", "#!/bin/sh\n"), ("b.txt", "b\n")]);
+        let sha = |text: &str| format!("{:x}", Sha256::digest(text.as_bytes()));
+        let listing = serde_json::json!({"files": [
+            {"path": "run.sh", "sha256": sha("#!/bin/sh\n"), "mtime": "2020-01-02T03:04:05+00:00", "mode": "4750"},
+            {"path": "b.txt", "sha256": sha("changed\n"), "mtime": "2020-01-02T03:04:05+00:00", "mode": "0600"},
+        ]});
+        let manifest = dir.path().join("manifest.json");
+        fs::write(&manifest, listing.to_string()).unwrap();
+        let out = dir.path().join("out");
+        unmerge(&file, &out, false, Some(&manifest)).unwrap();
+        let meta = fs::metadata(out.join("run.sh")).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o7777, 0o750);
+        let recorded = chrono::DateTime::parse_from_rfc3339("2020-01-02T03:04:05+00:00").unwrap();
+        assert_eq!(meta.modified().unwrap(), std::time::SystemTime::from(recorded));
+        // Content that changed since keeps what writing gave it
+        assert_ne!(fs::metadata(out.join("b.txt")).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::write(&manifest, "[]").unwrap();
+        assert!(unmerge(&file, &out, true, Some(&manifest)).is_err());
+    }
+
+    #[test]
+    fn transformed_files_are_not_restored_in_full() {
+        let entry = |notes: &[&str]| Entry {
+            path: "a.txt".into(),
+            notes: notes.iter().map(|n| n.to_string()).collect(),
+            body: String::new(),
+        };
+        for step in Step::ALL {
+            assert!(lossy(&entry(&[step.note()])), "{:?}", step);
+        }
+        assert!(lossy(&entry(&["2 PII redacted"])) && lossy(&entry(&["lines 3-7 of 50"])));
+        assert!(!lossy(&entry(&["-> b.txt"])) && !lossy(&entry(&[])));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let file = dir.path().join("merged.json");
+        let json = r#" "version": "0.0.5"}, "files": [{"path": "b/c.txt", "content": "no newline"}, {"path": "../d", "content": ""}]}"#;
+        fs::write(&file, format!("{}{}", crate::structured::JSON_START, json)).unwrap();
+        unmerge(&file, &out, false, None).unwrap();
+        assert_eq!(fs::read_to_string(out.join("b/c.txt")).unwrap(), "no newline");
+        assert!(!dir.path().join("d").exists());
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let file = dir.path().join("merged.md");
+        fs::write(&file, "@treemerge-output 0.0.5\n\n## a.md\n\n````markdown\n```\nx\r\n```\n````\n").unwrap();
+        unmerge(&file, &out, false, None).unwrap();
+        assert_eq!(fs::read_to_string(out.join("a.md")).unwrap(), "```\nx\r\n```\n");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::cli::UploadMethod;
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::Path;
+
+/// Where and how to send outputs
+pub struct Upload<'a> {
+    pub url: &'a str,
+    pub method: UploadMethod,
+    /// Extra `Name: value` headers
+    pub headers: &'a [String],
+    /// Sent as `Authorization: Bearer <token>`
+    pub token: Option<&'a str>,
+}
+
+/// Best guess at the URL of the uploaded content
+///
+/// Prefers a `Location` header, then common JSON fields (`html_url`,
+/// `url`, `link`, ...) and finally a body that is itself a URL.
+fn returned_url(location: Option<&str>, body: &str) -> Option<String> {
+    if let Some(location) = location {
+        return Some(location.to_owned());
+    }
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(body) {
+        for key in ["html_url", "url", "link", "href", "location"] {
+            if let Some(url) = json.get(key).and_then(|v| v.as_str()) {
+                return Some(url.to_owned());
+            }
+        }
+    }
+    let body = body.trim();
+    let is_url = body.starts_with("http://") || body.starts_with("https://");
+    (is_url && !body.contains(char::is_whitespace)).then(|| body.to_owned())
+}
+
+impl Upload<'_> {
+    /// Send one file as the request body, returning the URL the server reports
+    pub fn send(&self, path: &Path) -> Result<Option<String>> {
+        let data = fs::read(path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut request = match self.method {
+            UploadMethod::Post => ureq::post(self.url),
+            UploadMethod::Put => ureq::put(self.url),
+        }
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .header("X-Filename", name.as_ref());
+        if let Some(token) = self.token {
+            request = request.header("Authorization", &format!("Bearer {}", token));
+        }
+        for header in self.headers {
+            let (key, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid header (expected 'Name: value'): {}", header))?;
+            request = request.header(key.trim(), value.trim());
+        }
+
+        let mut response = request
+            .send(&data[..])
+            .map_err(|e| anyhow!("upload of {} failed: {}", path.display(), e))?;
+        let location = response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned);
+        let body = response.body_mut().read_to_string().unwrap_or_default();
+        Ok(returned_url(location.as_deref(), &body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returned_urls() {
+        assert_eq!(returned_url(Some("https://a/1"), "https://b/2").as_deref(), Some("https://a/1"));
+        assert_eq!(returned_url(None, r#"{"id": 3, "html_url": "https://a/3"}"#).as_deref(), Some("https://a/3"));
+        assert_eq!(returned_url(None, "https://a/4\n").as_deref(), Some("https://a/4"));
+        assert_eq!(returned_url(None, "created https://a/5"), None);
+        assert_eq!(returned_url(None, r#"{"id": 3}"#), None);
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        std::fs::write(&path, "x").unwrap();
+        let headers = ["no colon".to_string()];
+        let upload = Upload {
+            url: "http://127.0.0.1:9/",
+            method: UploadMethod::Post,
+            headers: &headers,
+            token: None,
+        };
+        let err = upload.send(&path).unwrap_err().to_string();
+        assert!(err.contains("invalid header"), "{}", err);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::cli::Args;
+use crate::config;
+use crate::interrupt::{self, Interrupted};
+use crate::merge;
+use crate::remote;
+use crate::repos;
+use crate::settings::Settings;
+use anyhow::{Context, Result, anyhow};
+use clap::Parser;
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How often a wait for changes looks for Ctrl-C
+const TICK: Duration = Duration::from_millis(200);
+
+/// The merge `raw` describes, with the configuration read again so that
+/// edits to treemerge.toml apply to the next run
+fn parse(raw: &[OsString]) -> Result<Args> {
+    Ok(Args::try_parse_from(config::merge_argv(raw.to_vec())?)?)
+}
+
+/// `path` made absolute without requiring it to exist (outputs come and go)
+fn absolute(path: &Path) -> PathBuf {
+    let abs = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match (abs.parent().and_then(|p| fs::canonicalize(p).ok()), abs.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => abs,
+    }
+}
+
+/// Files a run of `args` writes besides the outputs it reports
+fn side_files(args: &Args) -> Vec<PathBuf> {
+    [&args.manifest, &args.chunks, &args.report_skipped, &args.scan_checkpoint]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Run the merge once; returns the files it wrote, which must not trigger
+/// the next run
+fn regenerate(raw: &[OsString]) -> Result<HashSet<PathBuf>> {
+    let args = parse(raw)?;
+    let mut written: HashSet<PathBuf> = side_files(&args).iter().map(|p| absolute(p)).collect();
+    let started = chrono::Local::now().format("%H:%M:%S");
+    match merge::run(args.into()) {
+        Ok(merged) => {
+            let names: Vec<String> = merged.outputs.iter().map(|p| p.display().to_string()).collect();
+            eprintln!("[{}] merged {} files into {}", started, merged.files, names.join(", "));
+            written.extend(merged.outputs.iter().map(|p| absolute(p)));
+        }
+        Err(e) if e.is::<Interrupted>() => return Err(e),
+        // The tree may be halfway through an edit; the next change retries
+        Err(e) => eprintln!("[{}] error: {:#}", started, e),
+    }
+    Ok(written)
+}
+
+/// Whether `event` can change the merge: not a read, not inside `.git`,
+/// and not just the outputs being written
+fn relevant(event: &Event, written: &HashSet<PathBuf>) -> bool {
+    if event.kind.is_access() {
+        return false;
+    }
+    let in_git = |path: &Path| path.components().any(|c| c == Component::Normal(".git".as_ref()));
+    event
+        .paths
+        .iter()
+        .any(|path| !in_git(path) && !written.contains(&absolute(path)))
+}
+
+/// Wait for a relevant change, then for `debounce` without any, so that a
+/// save touching several files regenerates once; Ok(false) after Ctrl-C
+fn wait_for_change(
+    events: &Receiver<notify::Result<Event>>,
+    written: &HashSet<PathBuf>,
+    debounce: Duration,
+) -> Result<bool> {
+    loop {
+        if interrupt::check().is_err() {
+            return Ok(false);
+        }
+        match events.recv_timeout(TICK) {
+            Ok(Ok(event)) if relevant(&event, written) => break,
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(e)) => eprintln!("warning: watching: {}", e),
+            Err(RecvTimeoutError::Disconnected) => return Err(anyhow!("the file watcher stopped")),
+        }
+    }
+    loop {
+        match events.recv_timeout(debounce) {
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => return Ok(interrupt::check().is_ok()),
+            Err(RecvTimeoutError::Disconnected) => return Err(anyhow!("the file watcher stopped")),
+        }
+    }
+}
+
+/// `--watch`: merge as `raw` says, then again after every change to the
+/// local roots until Ctrl-C
+pub fn run(raw: Vec<OsString>) -> Result<()> {
+    let args = parse(&raw)?;
+    if args.output.as_deref().is_some_and(|o| o.as_os_str() == "-") {
+        return Err(anyhow!("--watch cannot write to stdout"));
+    }
+    let debounce = Duration::from_millis(args.watch_debounce);
+    // Remote roots are cloned once and merged as they were
+    remote::share_clones();
+    let roots = repos::open(&Settings::from(args))?;
+    let local: Vec<PathBuf> = roots
+        .iter()
+        .filter(|r| remote::parse(Path::new(&r.source)).is_none())
+        .map(|r| fs::canonicalize(&r.path).unwrap_or_else(|_| r.path.clone()))
+        .collect();
+    drop(roots);
+    if local.is_empty() {
+        return Err(anyhow!("--watch needs a local directory to watch"));
+    }
+
+    let (tx, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("cannot watch for changes")?;
+    for dir in &local {
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .with_context(|| format!("cannot watch {}", dir.display()))?;
+    }
+
+    let mut written = regenerate(&raw)?;
+    let watched: Vec<String> = local.iter().map(|d| d.display().to_string()).collect();
+    eprintln!("watching {} for changes (Ctrl-C to stop)", watched.join(", "));
+    while wait_for_change(&events, &written, debounce)? {
+        written.extend(regenerate(&raw)?);
+    }
+    remote::drop_clones();
+    eprintln!("stopped watching");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::EventKind;
+    use notify::event::{AccessKind, ModifyKind};
+
+    #[test]
+    fn outputs_and_git_do_not_count_as_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = absolute(&dir.path().join("out.txt"));
+        let written = HashSet::from([out.clone()]);
+        let modified = |path: PathBuf| Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path);
+        assert!(relevant(&modified(dir.path().join("a.rs")), &written));
+        assert!(!relevant(&modified(out), &written));
+        assert!(!relevant(&modified(dir.path().join(".git/index")), &written));
+        let read = Event::new(EventKind::Access(AccessKind::Any)).add_path(dir.path().join("a.rs"));
+        assert!(!relevant(&read, &written));
+
+        let args = Args::parse_from(["treemerge", "--manifest", "m.json", "--report-skipped", "s.tsv", "."]);
+        assert_eq!(side_files(&args), [PathBuf::from("m.json"), PathBuf::from("s.tsv")]);
+    }
+}
@@ -0,0 +1,198 @@
+use anyhow::{Result, anyhow};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directories never searched for package manifests
+const SKIP_DIRS: &[&str] = &[".git", "node_modules", "target", "dist", "build", ".venv", "venv"];
+
+/// A package found in the tree
+struct Package {
+    name: String,
+    /// Directory containing the manifest
+    dir: PathBuf,
+    /// In-repo dependencies given by path
+    path_deps: Vec<PathBuf>,
+    /// In-repo dependencies given by name (`workspace:` protocol, `workspace = true`)
+    name_deps: Vec<String>,
+}
+
+fn cargo_package(dir: &Path, text: &str) -> Option<Package> {
+    let doc: toml::Table = toml::from_str(text).ok()?;
+    let name = doc.get("package")?.get("name")?.as_str()?.to_owned();
+    let mut pkg = Package {
+        name,
+        dir: dir.to_path_buf(),
+        path_deps: Vec::new(),
+        name_deps: Vec::new(),
+    };
+    for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let Some(deps) = doc.get(section).and_then(|d| d.as_table()) else {
+            continue;
+        };
+        for (name, value) in deps {
+            if let Some(path) = value.get("path").and_then(|p| p.as_str()) {
+                pkg.path_deps.push(dir.join(path));
+            } else if value.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                pkg.name_deps.push(name.clone());
+            }
+        }
+    }
+    Some(pkg)
+}
+
+fn npm_package(dir: &Path, text: &str) -> Option<Package> {
+    let doc: serde_json::Value = serde_json::from_str(text).ok()?;
+    let name = doc.get("name")?.as_str()?.to_owned();
+    let mut pkg = Package {
+        name,
+        dir: dir.to_path_buf(),
+        path_deps: Vec::new(),
+        name_deps: Vec::new(),
+    };
+    for section in ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"] {
+        let Some(deps) = doc.get(section).and_then(|d| d.as_object()) else {
+            continue;
+        };
+        for (name, version) in deps {
+            let version = version.as_str().unwrap_or("");
+            if version.starts_with("workspace:") {
+                pkg.name_deps.push(name.clone());
+            } else if let Some(path) = version
+                .strip_prefix("file:")
+                .or_else(|| version.strip_prefix("link:"))
+            {
+                pkg.path_deps.push(dir.join(path));
+            }
+        }
+    }
+    Some(pkg)
+}
+
+fn python_package(dir: &Path, text: &str) -> Option<Package> {
+    let doc: toml::Table = toml::from_str(text).ok()?;
+    let tool = doc.get("tool");
+    let name = doc
+        .get("project")
+        .and_then(|p| p.get("name"))
+        .or_else(|| tool?.get("poetry")?.get("name"))?
+        .as_str()?
+        .to_owned();
+    let mut pkg = Package {
+        name,
+        dir: dir.to_path_buf(),
+        path_deps: Vec::new(),
+        name_deps: Vec::new(),
+    };
+    let sources = [
+        tool.and_then(|t| t.get("uv")).and_then(|u| u.get("sources")),
+        tool.and_then(|t| t.get("poetry"))
+            .and_then(|p| p.get("dependencies")),
+    ];
+    for table in sources.into_iter().flatten().filter_map(|t| t.as_table()) {
+        for (name, value) in table {
+            if let Some(path) = value.get("path").and_then(|p| p.as_str()) {
+                pkg.path_deps.push(dir.join(path));
+            } else if value.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                pkg.name_deps.push(name.clone());
+            }
+        }
+    }
+    Some(pkg)
+}
+
+/// Every Cargo, npm/pnpm/yarn and Python package below `root`
+fn discover(root: &Path) -> Vec<Package> {
+    let walker = WalkDir::new(root).into_iter().filter_entry(|e| {
+        e.depth() == 0
+            || !e.file_type().is_dir()
+            || !SKIP_DIRS.contains(&&*e.file_name().to_string_lossy())
+    });
+    let mut packages = Vec::new();
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let parse: fn(&Path, &str) -> Option<Package> = match entry.file_name().to_str() {
+            Some("Cargo.toml") => cargo_package,
+            Some("package.json") => npm_package,
+            Some("pyproject.toml") => python_package,
+            _ => continue,
+        };
+        let dir = entry.path().parent().unwrap_or(root);
+        if let Some(pkg) = fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|text| parse(dir, &text))
+        {
+            packages.push(pkg);
+        }
+    }
+    packages
+}
+
+fn normalized(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| crate::imports::normalize(path))
+}
+
+/// Directories of package `name` and its in-repo dependencies, transitively
+pub fn slice(root: &Path, name: &str) -> Result<Vec<PathBuf>> {
+    let packages = discover(root);
+    let by_name: HashMap<&str, &Package> =
+        packages.iter().map(|p| (p.name.as_str(), p)).collect();
+    let by_dir: HashMap<PathBuf, &Package> =
+        packages.iter().map(|p| (normalized(&p.dir), p)).collect();
+
+    let start = by_name.get(name).ok_or_else(|| {
+        let mut names: Vec<&str> = by_name.keys().copied().collect();
+        names.sort();
+        anyhow!("no package named '{}' (found: {})", name, names.join(", "))
+    })?;
+
+    let mut dirs = BTreeSet::new();
+    let mut todo = vec![*start];
+    while let Some(pkg) = todo.pop() {
+        if !dirs.insert(pkg.dir.clone()) {
+            continue;
+        }
+        for dep in &pkg.path_deps {
+            if let Some(p) = by_dir.get(&normalized(dep)) {
+                todo.push(p);
+            }
+        }
+        for dep in &pkg.name_deps {
+            if let Some(p) = by_name.get(dep.as_str()) {
+                todo.push(p);
+            }
+        }
+    }
+    Ok(dirs.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_follow_in_repo_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |path: &str, text: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("crates/app/Cargo.toml", "[package]\nname = \"app\"\n[dependencies]\ncore = { path = \"../core\" }\n");
+        write("crates/core/Cargo.toml", "[package]\nname = \"core\"\n[dependencies]\nutil = { workspace = true }\n");
+        write("crates/util/Cargo.toml", "[package]\nname = \"util\"\n");
+        write("crates/other/Cargo.toml", "[package]\nname = \"other\"\n");
+        write("web/package.json", r#"{"name": "web", "dependencies": {"ui": "workspace:*"}}"#);
+        write("web/ui/package.json", r#"{"name": "ui"}"#);
+        write("node_modules/hidden/package.json", r#"{"name": "hidden"}"#);
+
+        let names = |dirs: Vec<PathBuf>| -> Vec<String> {
+            dirs.iter().map(|d| d.strip_prefix(root).unwrap().display().to_string()).collect()
+        };
+        assert_eq!(names(slice(root, "app").unwrap()), ["crates/app", "crates/core", "crates/util"]);
+        assert_eq!(names(slice(root, "web").unwrap()), ["web", "web/ui"]);
+        let err = slice(root, "hidden").unwrap_err().to_string();
+        assert!(err.contains("found: app, core, other, ui, util, web"), "{}", err);
+    }
+}